@@ -1,6 +1,6 @@
 use chrono::{DateTime, NaiveDate, Utc, Weekday};
 use lazy_static::lazy_static;
-use maiq_shared::{default::DefaultGroup, Fetch, Snapshot};
+use maiq_shared::{default::DefaultGroup, Fetch, Lesson, Snapshot};
 use reqwest::StatusCode;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
@@ -21,6 +21,59 @@ pub struct Poll {
   pub next_update: DateTime<Utc>,
 }
 
+/// Poll narrowed down to a single group
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GroupPoll {
+  pub today: Option<GroupPollDay>,
+  pub next: Option<GroupPollDay>,
+  pub next_update: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GroupPollDay {
+  /// Uid of the snapshot
+  pub uid: String,
+  pub date: DateTime<Utc>,
+  /// Uid of the group in the snapshot, `None` if the group isn't there
+  pub group_uid: Option<String>,
+  /// Whether the group changed in the last update
+  pub changed: bool,
+  pub diff: Option<GroupDiff>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GroupDiff {
+  pub name: String,
+  pub added: Vec<Lesson>,
+  pub removed: Vec<Lesson>,
+  pub changed: Vec<LessonChange>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LessonChange {
+  pub num: u8,
+  #[serde(default)]
+  pub subgroup: Option<u8>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub name: Option<Change<String>>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub teacher: Option<Change<Option<String>>>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub classroom: Option<Change<Option<String>>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Change<T> {
+  pub from: T,
+  pub to: T,
+}
+
+impl GroupDiff {
+  pub fn is_empty(&self) -> bool {
+    self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+  }
+}
+
 lazy_static! {
   static ref API_HOST: String = std::env::var("API_HOST").expect("API host not set");
   static ref TODAY_URL: String = format!("{}/latest/today", *API_HOST);
@@ -61,6 +114,10 @@ pub async fn poll() -> Result<Poll, ApiError> {
   get(&*POLL_URL).await
 }
 
+pub async fn group_poll(group: &str) -> Result<GroupPoll, ApiError> {
+  get(&format!("{}/{}", *POLL_URL, group)).await
+}
+
 async fn get<O: DeserializeOwned>(url: &str) -> Result<O, ApiError> {
   let res = reqwest::get(url).await?;
   match res.status() {
@@ -26,15 +26,27 @@ pub enum ApiError {
   #[error("Requested snapshot `{0}` not found")]
   SnapshotNotFound(String),
 
+  #[error("Group `{0}` not found")]
+  GroupNotFound(String),
+
   #[error("Requested default for `{1}` for `{0}` not found")]
   DefaultNotFound(String, String),
 
+  #[error("Webhook `{0}` not found")]
+  WebhookNotFound(String),
+
+  #[error("Snapshot for `{0}` is not pinned")]
+  PinNotFound(String),
+
   #[error("Invalid query param provided. Param value is `{0}`")]
   InvalidQueryParam(String),
 
   #[error("Invalid API Key")]
   InvalidApiKey,
 
+  #[error("Expected WebSocket upgrade request")]
+  UpgradeRequired,
+
   #[error("Internal server error")]
   Unknown,
 }
@@ -57,9 +69,13 @@ impl ApiError {
       ApiError::NotFound { .. } => Status::NotFound,
       ApiError::Database(..) => Status::InternalServerError,
       ApiError::SnapshotNotFound(..) => Status::NotFound,
+      ApiError::GroupNotFound(..) => Status::NotFound,
       ApiError::DefaultNotFound(..) => Status::NotFound,
+      ApiError::WebhookNotFound(..) => Status::NotFound,
+      ApiError::PinNotFound(..) => Status::NotFound,
       ApiError::InvalidQueryParam(..) => Status::BadRequest,
       ApiError::InvalidApiKey => Status::Unauthorized,
+      ApiError::UpgradeRequired => Status::UpgradeRequired,
       ApiError::Unknown => Status::InternalServerError,
     }
   }
@@ -69,9 +85,13 @@ impl ApiError {
       ApiError::NotFound { .. } => "route_not_matched",
       ApiError::Database(..) => "db_err",
       ApiError::SnapshotNotFound(..) => "snapshot_not_found",
+      ApiError::GroupNotFound(..) => "group_not_found",
       ApiError::DefaultNotFound(..) => "default_not_found",
+      ApiError::WebhookNotFound(..) => "webhook_not_found",
+      ApiError::PinNotFound(..) => "pin_not_found",
       ApiError::InvalidQueryParam(..) => "invalid_query_param",
       ApiError::InvalidApiKey => "invalid_api_key",
+      ApiError::UpgradeRequired => "upgrade_required",
       ApiError::Unknown => "unknown",
     }
   }
@@ -0,0 +1,40 @@
+use rocket::{
+  http::{Header, Status},
+  response::{self, Responder},
+  Request, Response,
+};
+
+/// Responds with `ETag` header, or with `304 Not Modified` if the tag is listed in `If-None-Match`
+pub struct ETagged<R> {
+  inner: R,
+  tag: String,
+}
+
+impl<R> ETagged<R> {
+  pub fn new(inner: R, tag: &str) -> Self {
+    ETagged { inner, tag: format!("\"{}\"", tag) }
+  }
+}
+
+/// `If-None-Match` uses weak comparison, so `W/` prefix is ignored
+fn matches(header: &str, tag: &str) -> bool {
+  header
+    .split(',')
+    .map(|x| x.trim())
+    .any(|x| x == "*" || x.trim_start_matches("W/") == tag)
+}
+
+impl<'r, 'o: 'r, R: Responder<'r, 'o>> Responder<'r, 'o> for ETagged<R> {
+  fn respond_to(self, req: &'r Request<'_>) -> response::Result<'o> {
+    let etag = Header::new("ETag", self.tag.clone());
+    if req
+      .headers()
+      .get("If-None-Match")
+      .any(|header| matches(header, &self.tag))
+    {
+      return Response::build().status(Status::NotModified).header(etag).ok();
+    }
+
+    Response::build_from(self.inner.respond_to(req)?).header(etag).ok()
+  }
+}
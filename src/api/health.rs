@@ -0,0 +1,100 @@
+use chrono::{DateTime, Utc};
+use maiq_parser::{utils::time::now, Fetch};
+use rocket::{http::Status, serde::json::Json};
+use serde::Serialize;
+
+use crate::env;
+
+use super::{CachePool, MongoPool};
+
+#[derive(Serialize)]
+pub struct Liveness {
+  pub status: &'static str,
+}
+
+#[derive(Serialize)]
+pub struct Readiness {
+  pub ready: bool,
+  pub database: DatabaseCheck,
+  pub updater: UpdaterCheck,
+  pub snapshots: SnapshotAges,
+}
+
+#[derive(Serialize)]
+pub struct DatabaseCheck {
+  pub ok: bool,
+  pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct UpdaterCheck {
+  pub ok: bool,
+  pub last_update: Option<DateTime<Utc>>,
+  pub age_secs: Option<i64>,
+  pub age_limit_secs: i64,
+  pub next_update: Option<DateTime<Utc>>,
+  pub grace_secs: i64,
+}
+
+#[derive(Serialize)]
+pub struct SnapshotAges {
+  pub today: Option<SnapshotAge>,
+  pub next: Option<SnapshotAge>,
+}
+
+#[derive(Serialize)]
+pub struct SnapshotAge {
+  pub uid: String,
+  pub parsed_date: DateTime<Utc>,
+  pub age_secs: i64,
+}
+
+#[get("/live")]
+pub async fn live() -> Json<Liveness> {
+  Json(Liveness { status: "ok" })
+}
+
+#[get("/ready")]
+pub async fn ready(db: &MongoPool, cache: &CachePool) -> (Status, Json<Readiness>) {
+  let database = match db.ping().await {
+    Ok(_) => DatabaseCheck { ok: true, error: None },
+    Err(err) => DatabaseCheck { ok: false, error: Some(err.to_string()) },
+  };
+
+  let now = now();
+  let limit = *env::update_age_limit();
+  let grace = *env::update_grace();
+  let cache = cache.read().await;
+  let status = cache.status();
+
+  // Last success may be old without failures in between only because of long or `off` windows in `UPDATE_SCHEDULE`,
+  // so the age limit applies while updates are failing. Overdue `next_update` means the updater task itself is dead
+  let last_update = cache.last_update();
+  let age = last_update.map(|t| now - t);
+  let fresh = match age {
+    Some(age) => status.consecutive_failures == 0 || age <= limit,
+    None => false,
+  };
+  let on_time = status.next_update.is_some_and(|next| now <= next + grace);
+  let updater = UpdaterCheck {
+    ok: fresh && on_time,
+    last_update,
+    age_secs: age.map(|age| age.num_seconds()),
+    age_limit_secs: limit.num_seconds(),
+    next_update: status.next_update,
+    grace_secs: grace.num_seconds(),
+  };
+
+  let snapshot_age = |fetch| {
+    cache.poll_snapshot(fetch).map(|s| SnapshotAge {
+      uid: s.uid.clone(),
+      parsed_date: s.parsed_date,
+      age_secs: (now - s.parsed_date).num_seconds(),
+    })
+  };
+  let snapshots = SnapshotAges { today: snapshot_age(Fetch::Today), next: snapshot_age(Fetch::Next) };
+
+  let ready = database.ok && updater.ok;
+  let status = if ready { Status::Ok } else { Status::ServiceUnavailable };
+  (status, Json(Readiness { ready, database, updater, snapshots }))
+}
@@ -6,7 +6,7 @@ use mongodb::bson::DateTime;
 use rocket::{
   http::Status,
   request::{FromParam, FromRequest, Outcome},
-  Request, State,
+  Request, Route, State,
 };
 
 use tokio::sync::RwLock;
@@ -20,11 +20,78 @@ use maiq_parser::Fetch;
 use self::error::ApiError;
 
 pub mod error;
+pub mod etag;
+pub mod health;
+pub mod openapi;
 pub mod routes;
+pub mod v2;
 
 type CachePool = State<Arc<RwLock<cache::CachePool>>>;
 type MongoPool = State<mongo::MongoPool>;
 
+/// Routes are the same in every API version, unless the version changes their response shape
+fn shared() -> Vec<Route> {
+  use routes::*;
+  routes![
+    index,
+    latest,
+    latest_group,
+    latest_teacher,
+    group_poll,
+    status,
+    stream,
+    ws,
+    snapshot_by_date,
+    revisions_by_date,
+    teacher_by_date,
+    snapshots_range,
+    snapshot_by_id,
+    snapshot_diff,
+    group_calendar,
+    group_calendar_by_date,
+    latest_effective,
+    effective_by_date,
+    week,
+    week_by_date,
+    latest_rooms,
+    rooms_by_date,
+    default,
+    groups,
+    openapi::openapi,
+    openapi::docs
+  ]
+}
+
+pub fn v1() -> Vec<Route> {
+  [shared(), routes![routes::poll]].concat()
+}
+
+pub fn v2() -> Vec<Route> {
+  [shared(), routes![v2::poll_v2]].concat()
+}
+
+pub fn health() -> Vec<Route> {
+  routes![health::live, health::ready]
+}
+
+pub fn dev() -> Vec<Route> {
+  use routes::*;
+  routes![
+    cached,
+    cache_info,
+    evict_cached,
+    clear_cache,
+    reset_poll,
+    refresh,
+    put_snapshot,
+    put_group,
+    release_snapshot,
+    list_webhooks,
+    add_webhook,
+    remove_webhook
+  ]
+}
+
 #[derive(Debug)]
 pub struct FetchParam(Fetch);
 
@@ -74,9 +141,9 @@ impl<'r> FromRequest<'r> for ApiKey {
 
   async fn from_request(req: &'r Request<'_>) -> Outcome<ApiKey, Self::Error> {
     match req.headers().get_one("x-api-key") {
-      None => Outcome::Failure((Status::Unauthorized, ApiError::InvalidApiKey)),
+      None => Outcome::Error((Status::Unauthorized, ApiError::InvalidApiKey)),
       Some(key) if key == env::api_secret() => Outcome::Success(ApiKey),
-      Some(_) => Outcome::Failure((Status::Unauthorized, ApiError::InvalidApiKey)),
+      Some(_) => Outcome::Error((Status::Unauthorized, ApiError::InvalidApiKey)),
     }
   }
 }
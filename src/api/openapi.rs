@@ -0,0 +1,675 @@
+use rocket::{
+  fairing::AdHoc,
+  response::content::RawHtml,
+  serde::json::{json, serde_json::Map, Json, Value},
+  Route, State,
+};
+
+/// OpenAPI document built from routes mounted at ignition, so paths and params can't drift from the code.
+/// Ignition fails if a mounted route isn't described
+pub struct OpenApiSpec(Value);
+
+pub fn fairing() -> AdHoc {
+  AdHoc::try_on_ignite("OpenAPI", |rocket| async move {
+    match spec(rocket.routes()) {
+      Ok(spec) => Ok(rocket.manage(OpenApiSpec(spec))),
+      Err(missing) => {
+        error!("Routes missing from the OpenAPI document: {}", missing.join(", "));
+        Err(rocket)
+      }
+    }
+  })
+}
+
+#[get("/openapi.json")]
+pub fn openapi(spec: &State<OpenApiSpec>) -> Json<&Value> {
+  Json(&spec.0)
+}
+
+#[get("/docs")]
+pub fn docs() -> RawHtml<&'static str> {
+  RawHtml(
+    r#"<!DOCTYPE html>
+<html>
+  <head>
+    <title>maiq-web API</title>
+    <meta charset="utf-8" />
+  </head>
+  <body>
+    <redoc spec-url="openapi.json"></redoc>
+    <script src="https://cdn.redoc.ly/redoc/latest/bundles/redoc.standalone.js"></script>
+  </body>
+</html>"#,
+  )
+}
+
+/// Names of undescribed routes on error
+fn spec<'a>(routes: impl Iterator<Item = &'a Route>) -> Result<Value, Vec<String>> {
+  let mut paths = Map::new();
+  let mut missing = vec![];
+  for route in routes {
+    let name = route.name.as_deref().unwrap_or_default();
+    let (summary, response) = match describe(name) {
+      Some(described) => described,
+      None => {
+        missing.push(name.to_string());
+        continue;
+      }
+    };
+    let base = route.uri.base().trim_matches('/').replace('/', "_");
+    let id = match base.is_empty() {
+      true => name.to_string(),
+      false => format!("{}_{}", base, name),
+    };
+
+    let mut op = json!({
+      "operationId": id,
+      "summary": summary,
+      "parameters": parameters(route),
+      "responses": {
+        "200": response,
+        "default": { "description": "Error", "content": json_content(schema_ref("CustomApiError")) }
+      }
+    });
+
+    if matches!(name, "latest" | "latest_group" | "snapshot_by_date" | "snapshot_by_id") {
+      op["responses"]["200"]["headers"] = json!({ "ETag": { "schema": { "type": "string" } } });
+      op["responses"]["304"] = json!({ "description": "Not modified, `If-None-Match` contains current `ETag`" });
+    }
+
+    if name == "ready" {
+      op["responses"]["503"] = json!({ "description": "Not ready", "content": json_content(schema_ref("Readiness")) });
+    }
+
+    if route.uri.base().ends_with("/dev") {
+      op["security"] = json!([{ "ApiKey": [] }]);
+    }
+
+    if let Some(schema) = request_body(name) {
+      op["requestBody"] = json!({ "required": true, "content": json_content(schema) });
+    }
+
+    let path = paths.entry(path(route.uri.path())).or_insert_with(|| json!({}));
+    path[route.method.as_str().to_lowercase()] = op;
+  }
+
+  if !missing.is_empty() {
+    return Err(missing);
+  }
+
+  Ok(json!({
+    "openapi": "3.0.3",
+    "info": { "title": "maiq-web", "version": env!("CARGO_PKG_VERSION") },
+    "paths": paths,
+    "components": {
+      "schemas": schemas(),
+      "securitySchemes": { "ApiKey": { "type": "apiKey", "in": "header", "name": "x-api-key" } }
+    }
+  }))
+}
+
+/// `/latest/<fetch>` -> `/latest/{fetch}`
+fn path(uri: &str) -> String {
+  uri
+    .split('/')
+    .map(|seg| match param(seg) {
+      Some(name) => format!("{{{}}}", name),
+      None => seg.to_string(),
+    })
+    .collect::<Vec<_>>()
+    .join("/")
+}
+
+/// Name of the dynamic segment, `<name>` or `<name..>`
+fn param(seg: &str) -> Option<&str> {
+  seg
+    .strip_prefix('<')
+    .and_then(|s| s.strip_suffix('>'))
+    .map(|s| s.trim_end_matches(".."))
+}
+
+fn parameters(route: &Route) -> Value {
+  let path = route
+    .uri
+    .path()
+    .split('/')
+    .filter_map(param)
+    .map(|name| json!({ "name": name, "in": "path", "required": true, "schema": param_schema(name) }));
+  let query = route
+    .uri
+    .query()
+    .unwrap_or_default()
+    .split('&')
+    .filter_map(param)
+    .map(|name| json!({ "name": name, "in": "query", "required": false, "schema": param_schema(name) }));
+
+  Value::Array(path.chain(query).collect())
+}
+
+fn param_schema(name: &str) -> Value {
+  match name {
+    "fetch" => json!({ "type": "string", "enum": ["today", "next", "tomorrow"] }),
+    "weekday" => json!({ "type": "string", "enum": ["mon", "tue", "wed", "thu", "fri", "sat", "sun"] }),
+    "date" | "from" | "to" => json!({ "type": "string", "pattern": "^\\d{1,2}\\.\\d{1,2}\\.\\d{4}$", "example": "19.01.2023" }),
+    "page" | "per_page" => json!({ "type": "integer", "minimum": 1 }),
+    "since" => json!({ "type": "integer" }),
+    _ => json!({ "type": "string" }),
+  }
+}
+
+fn describe(name: &str) -> Option<(&'static str, Value)> {
+  let ok = |summary, schema| (summary, json!({ "description": "OK", "content": json_content(schema) }));
+  let array = |schema| json!({ "type": "array", "items": schema });
+  let described = match name {
+    "index" => ok("Index", schema_ref("CustomApiError")),
+    "groups" => ok("Known groups", array(json!({ "type": "string" }))),
+    "default" => ok("Default schedule of the group for the weekday", schema_ref("DefaultGroup")),
+    "latest" => ok("Latest snapshot", schema_ref("Snapshot")),
+    "latest_group" => ok("Latest snapshot of the group", schema_ref("TinySnapshot")),
+    "latest_teacher" => ok("Lessons of the teacher in the latest snapshot", schema_ref("TeacherSnapshot")),
+    "poll" => (
+      "Changes found by the last update",
+      json!({
+        "description": "OK",
+        "headers": { "X-Poll-Token": { "description": "Pass as `since` to wait for the next poll", "schema": { "type": "integer" } } },
+        "content": json_content(schema_ref("Poll"))
+      }),
+    ),
+    "group_poll" => ok("Changes of the group found by the last update", schema_ref("GroupPoll")),
+    "status" => ok("State of the snapshot updater", schema_ref("UpdaterStatus")),
+    "poll_v2" => ok("Snapshots and changes found by the last update", schema_ref("PollV2")),
+    "snapshot_by_date" => ok("Latest revision of the snapshot for the date", schema_ref("Snapshot")),
+    "revisions_by_date" => ok("All revisions of the snapshot for the date", array(schema_ref("Revision"))),
+    "teacher_by_date" => ok("Lessons of the teacher for the date", schema_ref("TeacherSnapshot")),
+    "snapshots_range" => ok("Snapshots within the dates", schema_ref("Page")),
+    "snapshot_by_id" => ok("Snapshot by uid", schema_ref("Snapshot")),
+    "snapshot_diff" => ok("Lesson changes between two snapshots", schema_ref("SnapshotDiff")),
+    "latest_effective" | "effective_by_date" => ok("Effective schedule of the group", schema_ref("DaySchedule")),
+    "week" | "week_by_date" => ok("Effective schedule of the group for the week", array(schema_ref("DaySchedule"))),
+    "latest_rooms" | "rooms_by_date" => ok("Classroom occupancy", schema_ref("Rooms")),
+    "cached" => ok("Cached snapshots", array(schema_ref("Snapshot"))),
+    "put_snapshot" => ok("Publish snapshot for the date by hand", schema_ref("Snapshot")),
+    "put_group" => ok("Replace lessons of the group and publish snapshot by hand", schema_ref("Snapshot")),
+    "release_snapshot" => ("Let parsed snapshots for the date through again", json!({ "description": "Released" })),
+    "cache_info" => ok("Cached snapshots metadata", schema_ref("CacheInfo")),
+    "clear_cache" => {
+      ok("Drop all cached snapshots", json!({ "type": "object", "properties": { "removed": { "type": "integer" } } }))
+    }
+    "evict_cached" => ("Drop snapshot from cache", json!({ "description": "Evicted" })),
+    "reset_poll" => ("Drop poll state", json!({ "description": "Reset" })),
+    "refresh" => ok("Update snapshots right away", array(schema_ref("Refreshed"))),
+    "list_webhooks" => ok("Registered webhooks", array(schema_ref("Webhook"))),
+    "add_webhook" => ok("Register webhook", schema_ref("Webhook")),
+    "openapi" => ok("This document", json!({ "type": "object" })),
+    "live" => ok("Liveness probe", json!({ "type": "object", "properties": { "status": { "type": "string" } } })),
+    "ready" => ok("Readiness probe checking database and updater", schema_ref("Readiness")),
+    "group_calendar" | "group_calendar_by_date" => (
+      "Schedule of the group as iCalendar feed",
+      json!({ "description": "OK", "content": { "text/calendar": { "schema": { "type": "string" } } } }),
+    ),
+    "ws" => (
+      "WebSocket channel with group and teacher subscriptions",
+      json!({ "description": "Not an upgrade request, 101 Switching Protocols otherwise" }),
+    ),
+    "stream" => (
+      "Stream of `snapshot` events with SnapshotUpdate payload",
+      json!({ "description": "OK", "content": { "text/event-stream": { "schema": { "type": "string" } } } }),
+    ),
+    "prometheus" => (
+      "Metrics in Prometheus text format",
+      json!({ "description": "OK", "content": { "text/plain": { "schema": { "type": "string" } } } }),
+    ),
+    "docs" => (
+      "This document rendered with Redoc",
+      json!({ "description": "OK", "content": { "text/html": { "schema": { "type": "string" } } } }),
+    ),
+    "remove_webhook" => ("Remove webhook", json!({ "description": "Removed" })),
+    _ => return None,
+  };
+  Some(described)
+}
+
+fn request_body(name: &str) -> Option<Value> {
+  match name {
+    "add_webhook" => Some(schema_ref("NewWebhook")),
+    "put_snapshot" => Some(schema_ref("ManualSnapshot")),
+    "put_group" => Some(schema_ref("ManualLessons")),
+    _ => None,
+  }
+}
+
+fn json_content(schema: Value) -> Value {
+  json!({ "application/json": { "schema": schema } })
+}
+
+fn schema_ref(name: &str) -> Value {
+  json!({ "$ref": format!("#/components/schemas/{}", name) })
+}
+
+fn schemas() -> Value {
+  let string = json!({ "type": "string" });
+  let int = json!({ "type": "integer" });
+  let date = json!({ "type": "string", "format": "date-time" });
+  let strings = json!({ "type": "array", "items": string });
+  let group_poll_day = json!({
+    "type": "object",
+    "nullable": true,
+    "properties": {
+      "uid": string,
+      "date": date,
+      "group_uid": { "type": "string", "nullable": true },
+      "changed": { "type": "boolean" },
+      "diff": { "allOf": [schema_ref("GroupDiff")], "nullable": true }
+    }
+  });
+  let nullable_date = json!({ "type": "string", "format": "date-time", "nullable": true });
+  let snapshot_age = json!({
+    "type": "object",
+    "nullable": true,
+    "properties": { "uid": string, "parsed_date": date, "age_secs": int }
+  });
+  let change = json!({ "type": "object", "properties": { "from": { "nullable": true }, "to": { "nullable": true } } });
+
+  json!({
+    "Lesson": {
+      "type": "object",
+      "required": ["num", "name"],
+      "properties": { "num": int, "subgroup": int, "name": string, "teacher": string, "classroom": string }
+    },
+    "Group": {
+      "type": "object",
+      "required": ["uid", "name", "lessons"],
+      "properties": { "uid": string, "name": string, "lessons": { "type": "array", "items": schema_ref("Lesson") } }
+    },
+    "Snapshot": {
+      "type": "object",
+      "required": ["uid", "date", "parsed_date", "groups"],
+      "properties": {
+        "uid": string,
+        "date": date,
+        "parsed_date": date,
+        "groups": { "type": "array", "items": schema_ref("Group") }
+      }
+    },
+    "TinySnapshot": {
+      "type": "object",
+      "required": ["uid", "date", "parsed_date"],
+      "properties": { "uid": string, "date": date, "parsed_date": date, "group": schema_ref("Group") }
+    },
+    "DefaultLesson": {
+      "type": "object",
+      "required": ["num", "name"],
+      "properties": {
+        "num": int,
+        "is_even": { "type": "boolean" },
+        "subgroup": int,
+        "name": string,
+        "teacher": string,
+        "classroom": string
+      }
+    },
+    "DefaultGroup": {
+      "type": "object",
+      "required": ["name", "lessons"],
+      "properties": { "name": string, "lessons": { "type": "array", "items": schema_ref("DefaultLesson") } }
+    },
+    "Poll": {
+      "type": "object",
+      "required": ["today_changes", "next_changes", "next_update"],
+      "properties": { "today_changes": strings, "next_changes": strings, "next_update": date }
+    },
+    "PollV2": {
+      "type": "object",
+      "required": ["next_update", "token"],
+      "properties": {
+        "today": { "nullable": true, "allOf": [schema_ref("PollDay")] },
+        "next": { "nullable": true, "allOf": [schema_ref("PollDay")] },
+        "next_update": date,
+        "token": int
+      }
+    },
+    "PollDay": {
+      "type": "object",
+      "required": ["uid", "date", "changes"],
+      "properties": { "uid": string, "date": date, "changes": strings }
+    },
+    "CustomApiError": {
+      "type": "object",
+      "required": ["cause", "desc"],
+      "properties": { "cause": string, "desc": string }
+    },
+    "Revision": {
+      "type": "object",
+      "required": ["uid", "parsed_date", "manual"],
+      "properties": { "uid": string, "parsed_date": date, "manual": { "type": "boolean" } }
+    },
+    "SnapshotDiff": {
+      "type": "object",
+      "required": ["from", "to", "groups"],
+      "properties": {
+        "from": string,
+        "to": string,
+        "groups": { "type": "array", "items": schema_ref("GroupDiff") }
+      }
+    },
+    "GroupDiff": {
+      "type": "object",
+      "properties": {
+        "name": string,
+        "added": { "type": "array", "items": schema_ref("Lesson") },
+        "removed": { "type": "array", "items": schema_ref("Lesson") },
+        "changed": {
+          "type": "array",
+          "items": {
+            "type": "object",
+            "properties": {
+              "num": int,
+              "subgroup": { "type": "integer", "nullable": true },
+              "name": change,
+              "teacher": change,
+              "classroom": change
+            }
+          }
+        }
+      }
+    },
+    "GroupPoll": {
+      "type": "object",
+      "required": ["next_update"],
+      "properties": { "today": group_poll_day, "next": group_poll_day, "next_update": date }
+    },
+    "SnapshotUpdate": {
+      "type": "object",
+      "properties": { "fetch": { "type": "string", "enum": ["today", "next"] }, "uid": string, "date": date, "changes": strings }
+    },
+    "TeacherSnapshot": {
+      "type": "object",
+      "properties": {
+        "uid": string,
+        "date": date,
+        "parsed_date": date,
+        "teacher": string,
+        "lessons": {
+          "type": "array",
+          "items": {
+            "type": "object",
+            "properties": { "num": int, "group": string, "name": string, "teacher": string, "classroom": string }
+          }
+        }
+      }
+    },
+    "DaySchedule": {
+      "type": "object",
+      "properties": {
+        "date": date,
+        "group": string,
+        "source": { "type": "string", "enum": ["snapshot", "default", "none"] },
+        "uid": string,
+        "lessons": { "type": "array", "items": { "oneOf": [schema_ref("Lesson"), schema_ref("DefaultLesson")] } }
+      }
+    },
+    "Rooms": {
+      "type": "object",
+      "properties": {
+        "uid": string,
+        "date": date,
+        "lessons": {
+          "type": "array",
+          "items": {
+            "type": "object",
+            "properties": {
+              "num": int,
+              "free": strings,
+              "occupied": {
+                "type": "array",
+                "items": {
+                  "type": "object",
+                  "properties": { "classroom": string, "group": string, "name": string, "teacher": string }
+                }
+              }
+            }
+          }
+        }
+      }
+    },
+    "Page": {
+      "type": "object",
+      "properties": {
+        "page": int,
+        "per_page": int,
+        "has_more": { "type": "boolean" },
+        "items": { "type": "array", "items": { "oneOf": [schema_ref("Snapshot"), schema_ref("TinySnapshot")] } }
+      }
+    },
+    "Webhook": {
+      "type": "object",
+      "properties": { "id": string, "url": string }
+    },
+    "NewWebhook": {
+      "type": "object",
+      "required": ["url", "secret"],
+      "properties": { "url": string, "secret": string }
+    },
+    "ManualSnapshot": {
+      "type": "object",
+      "required": ["groups"],
+      "properties": {
+        "groups": {
+          "type": "array",
+          "items": {
+            "type": "object",
+            "required": ["name", "lessons"],
+            "properties": { "name": string, "lessons": { "type": "array", "items": schema_ref("Lesson") } }
+          }
+        }
+      }
+    },
+    "ManualLessons": {
+      "type": "object",
+      "required": ["lessons"],
+      "properties": { "lessons": { "type": "array", "items": schema_ref("Lesson") } }
+    },
+    "CacheInfo": {
+      "type": "object",
+      "properties": {
+        "size_limit": int,
+        "age_limit_secs": int,
+        "entries": {
+          "type": "array",
+          "items": {
+            "type": "object",
+            "properties": { "uid": string, "date": date, "added": date, "age_secs": int, "expired": { "type": "boolean" } }
+          }
+        }
+      }
+    },
+    "Refreshed": {
+      "type": "object",
+      "properties": {
+        "fetch": { "type": "string", "enum": ["today", "next"] },
+        "uid": { "type": "string", "nullable": true },
+        "changes": strings,
+        "error": { "type": "string", "nullable": true }
+      }
+    },
+    "UpdaterStatus": {
+      "type": "object",
+      "properties": {
+        "last_success": nullable_date,
+        "last_failure": nullable_date,
+        "last_error": { "type": "string", "nullable": true },
+        "consecutive_failures": int,
+        "next_update": nullable_date
+      }
+    },
+    "Readiness": {
+      "type": "object",
+      "properties": {
+        "ready": { "type": "boolean" },
+        "database": {
+          "type": "object",
+          "properties": { "ok": { "type": "boolean" }, "error": { "type": "string", "nullable": true } }
+        },
+        "updater": {
+          "type": "object",
+          "properties": {
+            "ok": { "type": "boolean" },
+            "last_update": nullable_date,
+            "age_secs": { "type": "integer", "nullable": true },
+            "age_limit_secs": int,
+            "next_update": nullable_date,
+            "grace_secs": int
+          }
+        },
+        "snapshots": {
+          "type": "object",
+          "properties": { "today": snapshot_age, "next": snapshot_age }
+        }
+      }
+    }
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use chrono::Utc;
+  use maiq_api_wrapper::{GroupDiff, GroupPoll, GroupPollDay};
+  use maiq_parser::{Fetch, Group, Lesson, Snapshot};
+  use rocket::{http::Status, serde::json::serde_json};
+  use serde::Serialize;
+
+  use super::*;
+  use crate::{
+    api::{self, error::CustomApiError, health, routes, v2},
+    schedule::{DaySchedule, Lessons, Source},
+    storage::{
+      cache::{CacheEntry, Refreshed, SnapshotUpdate, UpdaterStatus},
+      Revision,
+    },
+  };
+
+  fn mounted() -> Vec<Route> {
+    [api::v1(), api::v2(), api::dev(), api::health(), routes![routes::index, routes::prometheus]].concat()
+  }
+
+  fn assert_schema<T: Serialize>(name: &str, sample: &T) {
+    assert_properties(name, &schemas()[name], sample);
+  }
+
+  /// Serialized value must have exactly the properties of the schema, so samples fill every optional field
+  fn assert_properties<T: Serialize>(name: &str, schema: &Value, sample: &T) {
+    let value = serde_json::to_value(sample).unwrap();
+    let mut keys = value.as_object().unwrap().keys().cloned().collect::<Vec<_>>();
+    let mut properties = schema["properties"]
+      .as_object()
+      .unwrap()
+      .keys()
+      .cloned()
+      .collect::<Vec<_>>();
+    keys.sort();
+    properties.sort();
+    assert_eq!(keys, properties, "schema {} drifted from the type", name);
+
+    for required in schema["required"].as_array().into_iter().flatten() {
+      assert!(value.get(required.as_str().unwrap()).is_some(), "{}.{} is required", name, required);
+    }
+  }
+
+  fn refs(value: &Value, found: &mut Vec<String>) {
+    match value {
+      Value::Object(map) => {
+        if let Some(Value::String(path)) = map.get("$ref") {
+          found.push(path.trim_start_matches("#/components/schemas/").to_string());
+        }
+        map.values().for_each(|v| refs(v, found));
+      }
+      Value::Array(items) => items.iter().for_each(|v| refs(v, found)),
+      _ => (),
+    }
+  }
+
+  #[test]
+  fn every_mounted_route_is_described() {
+    let routes = mounted();
+    assert_eq!(spec(routes.iter()).err(), None);
+  }
+
+  #[test]
+  fn every_ref_resolves() {
+    let routes = mounted();
+    let spec = spec(routes.iter()).unwrap();
+    let mut found = vec![];
+    refs(&spec, &mut found);
+    for name in found {
+      assert!(spec["components"]["schemas"].get(&name).is_some(), "schema {} is missing", name);
+    }
+  }
+
+  #[test]
+  fn schemas_match_types() {
+    let now = Utc::now();
+    let some = || Some("x".to_string());
+    let lesson = Lesson { num: 1, subgroup: Some(1), name: "x".into(), teacher: some(), classroom: some() };
+    let group = Group { uid: "x".into(), name: "x".into(), lessons: vec![lesson.clone()] };
+    let snapshot = Snapshot { uid: "x".into(), date: now, parsed_date: now, groups: vec![group.clone()] };
+    let day = GroupPollDay {
+      uid: "x".into(),
+      date: now,
+      group_uid: some(),
+      changed: true,
+      diff: Some(GroupDiff { name: "x".into(), added: vec![], removed: vec![], changed: vec![] }),
+    };
+
+    assert_schema("Lesson", &lesson);
+    assert_schema("Group", &group);
+    assert_schema("Snapshot", &snapshot);
+    assert_schema("TinySnapshot", &snapshot.tiny("x"));
+    assert_schema("Poll", &maiq_api_wrapper::Poll::default());
+    assert_schema("PollV2", &v2::Poll { today: None, next: None, next_update: now, token: 0 });
+    assert_schema("PollDay", &v2::PollDay { uid: "x".into(), date: now, changes: vec![] });
+    assert_schema("CustomApiError", &CustomApiError { cause: "x", status: Status::Ok, desc: "x".into() });
+    assert_schema("Revision", &Revision { uid: "x".into(), parsed_date: now, manual: false });
+    assert_schema("GroupPoll", &GroupPoll { today: Some(day.clone()), next: None, next_update: now });
+    assert_schema("GroupDiff", day.diff.as_ref().unwrap());
+    assert_schema(
+      "DaySchedule",
+      &DaySchedule { date: now, group: "x".into(), source: Source::Snapshot, uid: some(), lessons: Lessons::Snapshot(vec![]) },
+    );
+    assert_schema(
+      "UpdaterStatus",
+      &UpdaterStatus {
+        last_success: Some(now),
+        last_failure: Some(now),
+        last_error: some(),
+        consecutive_failures: 1,
+        next_update: Some(now),
+      },
+    );
+    let update = SnapshotUpdate { fetch: Fetch::Next, uid: "x".into(), date: now, changes: vec![] };
+    assert_schema("SnapshotUpdate", &update);
+    assert_eq!(serde_json::to_value(&update).unwrap()["fetch"], "next");
+    assert_schema("Refreshed", &Refreshed { fetch: "today", uid: some(), changes: vec![], error: some() });
+    assert_schema("CacheInfo", &routes::CacheInfo { size_limit: 1, age_limit_secs: 1, entries: vec![] });
+    assert_properties(
+      "CacheEntry",
+      &schemas()["CacheInfo"]["properties"]["entries"]["items"],
+      &CacheEntry { uid: "x".into(), date: now, added: now, age_secs: 0, expired: false },
+    );
+    assert_schema(
+      "Readiness",
+      &health::Readiness {
+        ready: true,
+        database: health::DatabaseCheck { ok: true, error: None },
+        updater: health::UpdaterCheck {
+          ok: true,
+          last_update: None,
+          age_secs: None,
+          age_limit_secs: 0,
+          next_update: None,
+          grace_secs: 0,
+        },
+        snapshots: health::SnapshotAges { today: None, next: None },
+      },
+    );
+  }
+}
@@ -1,14 +1,45 @@
-use maiq_api_wrapper::Poll;
-use maiq_parser::{default::DefaultGroup, Snapshot, TinySnapshot};
-use rocket::{http::Status, serde::json::Json};
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, Utc};
+use maiq_api_wrapper::{GroupPoll, GroupPollDay, Poll};
+use maiq_parser::{
+  default::DefaultGroup,
+  utils::time::{now, now_date},
+  Fetch, Group, Lesson, Snapshot, TinySnapshot,
+};
+use mongodb::bson::oid::ObjectId;
+use rocket::{
+  http::{ContentType, Header, Status},
+  request::FromParam,
+  response::stream::{Event, EventStream},
+  serde::json::{serde_json, Json},
+  tokio::{select, sync::broadcast::error::RecvError},
+  Shutdown,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 use crate::{
   api::{map_weekday, CachePool, FetchParam, MongoPool},
-  storage::SnapshotPool,
+  env, metrics,
+  schedule::{
+    self,
+    diff::{self, SnapshotDiff},
+    ical,
+    rooms::{self, Rooms},
+    teacher::{teacher_snapshot, TeacherSnapshot},
+    DaySchedule,
+  },
+  storage::{
+    cache::{self, CacheEntry, Refreshed, UpdaterStatus},
+    Revision, SnapshotPool,
+  },
+  websocket::{Channel, WebSocket},
 };
 
 use super::{
   error::{ApiError, CustomApiError},
+  etag::ETagged,
   ApiKey, DateParam,
 };
 
@@ -32,19 +63,11 @@ pub fn default<'a>(weekday: &str, group: &'a str) -> Result<Json<&'a DefaultGrou
 }
 
 #[get("/latest/<fetch>")]
-pub async fn latest(fetch: FetchParam, db: &MongoPool, cache: &CachePool) -> Result<Json<Snapshot>, ApiError> {
-  if let Ok(Some(s)) = cache.read().await.latest(*fetch).await {
-    return Ok(Json(s));
-  }
-
-  info!("Trying to fetch {:?} snapshot from db", *fetch);
-  match db.latest(*fetch).await? {
-    Some(s) => {
-      cache.write().await.save(&s).await?;
-      Ok(Json(s))
-    }
-    None => Err(ApiError::SnapshotNotFound(format!("{:?}", fetch))),
-  }
+pub async fn latest(fetch: FetchParam, db: &MongoPool, cache: &CachePool) -> Result<ETagged<Json<Snapshot>>, ApiError> {
+  find_latest(*fetch, db, cache)
+    .await?
+    .map(tagged)
+    .ok_or_else(|| ApiError::SnapshotNotFound(format!("{:?}", fetch)))
 }
 
 #[get("/latest/<fetch>/<group>")]
@@ -53,51 +76,574 @@ pub async fn latest_group(
   group: &str,
   db: &MongoPool,
   cache: &CachePool,
-) -> Result<Json<TinySnapshot>, ApiError> {
-  if let Ok(Some(s)) = cache.read().await.latest(*fetch).await {
-    return Ok(Json(s.tiny(group)));
+) -> Result<ETagged<Json<TinySnapshot>>, ApiError> {
+  let tiny = find_latest(*fetch, db, cache)
+    .await?
+    .map(|s| s.tiny(group))
+    .ok_or_else(|| ApiError::SnapshotNotFound(format!("{:?}", fetch)))?;
+
+  // Body carries the snapshot uid and dates too, so the group uid alone isn't enough for a strong tag
+  let tag = match tiny.group.as_ref() {
+    Some(group) => format!("{}-{}", tiny.uid, group.uid),
+    None => tiny.uid.clone(),
+  };
+  Ok(ETagged::new(Json(tiny), &tag))
+}
+
+#[get("/latest/<fetch>/teacher/<name>")]
+pub async fn latest_teacher(
+  fetch: FetchParam,
+  name: &str,
+  db: &MongoPool,
+  cache: &CachePool,
+) -> Result<Json<TeacherSnapshot>, ApiError> {
+  find_latest(*fetch, db, cache)
+    .await?
+    .map(|s| Json(teacher_snapshot(&s, name)))
+    .ok_or_else(|| ApiError::SnapshotNotFound(format!("{:?}", fetch)))
+}
+
+#[derive(Responder)]
+pub struct Tokened<T> {
+  inner: Json<T>,
+  token: Header<'static>,
+}
+
+impl<T> Tokened<T> {
+  pub fn new(inner: T, token: u64) -> Self {
+    Tokened { inner: Json(inner), token: Header::new("X-Poll-Token", token.to_string()) }
   }
+}
 
-  info!("Trying to fetch {:?} snapshot from db", fetch);
-  match db.latest(*fetch).await? {
-    Some(s) => {
-      cache.write().await.save(&s).await?;
-      Ok(Json(s.tiny(group)))
+/// With `since` equal to the current token, holds the request until the next poll or `LONG_POLL_TIMEOUT_SECS`.
+/// Token is sent in `X-Poll-Token` header
+#[get("/poll?<since>")]
+pub async fn poll(since: Option<u64>, cache: &CachePool, shutdown: Shutdown) -> Result<Tokened<Poll>, ApiError> {
+  let (poll, token) = wait_poll(since, cache, shutdown).await;
+  Ok(Tokened::new(poll, token))
+}
+
+/// Poll and its token, waiting for a newer one if `since` is the current token
+pub async fn wait_poll(since: Option<u64>, cache: &CachePool, mut shutdown: Shutdown) -> (Poll, u64) {
+  let mut revision = cache.read().await.watch_poll();
+  if since.is_some() && since == Some(*revision.borrow_and_update()) {
+    let timeout = env::long_poll_timeout().to_std().unwrap_or_default();
+    select! {
+      _ = revision.changed() => (),
+      _ = rocket::tokio::time::sleep(timeout) => (),
+      _ = &mut shutdown => (),
     }
-    None => Err(ApiError::SnapshotNotFound(format!("{:?}", fetch))),
   }
+
+  let cache = cache.read().await;
+  (cache.poll(), cache.poll_revision())
+}
+
+#[get("/poll/<group>")]
+pub async fn group_poll(group: &str, cache: &CachePool) -> Result<Json<GroupPoll>, ApiError> {
+  check_group(group)?;
+  let cache = cache.read().await;
+  let day = |fetch| {
+    let snapshot = cache.poll_snapshot(fetch)?;
+    let current = snapshot.groups.iter().find(|g| g.name == group);
+    let replaced = cache
+      .replaced_snapshot(fetch)
+      .and_then(|s| s.groups.iter().find(|g| g.name == group));
+    let changed = cache.poll_changes(fetch).iter().any(|name| name == group);
+    Some(GroupPollDay {
+      uid: snapshot.uid.clone(),
+      date: snapshot.date,
+      group_uid: current.map(|g| g.uid.clone()),
+      changed,
+      diff: changed.then(|| diff::diff_group(group, replaced, current)).flatten(),
+    })
+  };
+
+  Ok(Json(GroupPoll { today: day(Fetch::Today), next: day(Fetch::Next), next_update: cache.next_update() }))
+}
+
+/// WebSocket channel with group and teacher subscriptions
+#[get("/ws")]
+pub async fn ws(ws: Result<WebSocket, ApiError>, cache: &CachePool, shutdown: Shutdown) -> Result<Channel, ApiError> {
+  Ok(ws?.channel(cache.inner().clone(), shutdown))
+}
+
+#[get("/status")]
+pub async fn status(cache: &CachePool) -> Json<UpdaterStatus> {
+  Json(cache.read().await.status())
 }
 
-#[get("/poll")]
-pub async fn poll(cache: &CachePool) -> Result<Json<Poll>, ApiError> {
-  Ok(Json(cache.read().await.poll()))
+/// Pushes `snapshot` event every time the updater finds a new snapshot or changes in the current one
+#[get("/stream?<group>")]
+pub async fn stream(group: Option<String>, cache: &CachePool, mut end: Shutdown) -> EventStream![] {
+  let mut updates = cache.read().await.subscribe();
+  EventStream! {
+    loop {
+      let update = select! {
+        update = updates.recv() => match update {
+          Ok(update) => update,
+          Err(RecvError::Closed) => break,
+          Err(RecvError::Lagged(_)) => continue,
+        },
+        _ = &mut end => break,
+      };
+
+      if group.as_ref().map(|g| update.changes.contains(g)).unwrap_or(true) {
+        yield Event::json(&update).event("snapshot");
+      }
+    }
+  }
 }
 
 #[get("/date/<date>")]
-pub async fn snapshot_by_date(date: Result<DateParam, ApiError>, db: &MongoPool) -> Result<Json<Snapshot>, ApiError> {
+pub async fn snapshot_by_date(date: Result<DateParam, ApiError>, db: &MongoPool) -> Result<ETagged<Json<Snapshot>>, ApiError> {
   let date = date?.0;
   db.by_date(date)
     .await?
-    .map(Json)
+    .map(tagged)
+    .ok_or_else(|| ApiError::SnapshotNotFound(format!("{}", date)))
+}
+
+#[get("/date/<date>/revisions")]
+pub async fn revisions_by_date(date: Result<DateParam, ApiError>, db: &MongoPool) -> Result<Json<Vec<Revision>>, ApiError> {
+  let date = date?.0;
+  let revisions = db.revisions(date).await?;
+  if revisions.is_empty() {
+    return Err(ApiError::SnapshotNotFound(format!("{}", date)));
+  }
+
+  Ok(Json(revisions))
+}
+
+#[get("/date/<date>/teacher/<name>")]
+pub async fn teacher_by_date(
+  date: Result<DateParam, ApiError>,
+  name: &str,
+  db: &MongoPool,
+) -> Result<Json<TeacherSnapshot>, ApiError> {
+  let date = date?.0;
+  db.by_date(date)
+    .await?
+    .map(|s| Json(teacher_snapshot(&s, name)))
     .ok_or_else(|| ApiError::SnapshotNotFound(format!("{}", date)))
 }
 
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum RangeItems {
+  Snapshots(Vec<Snapshot>),
+  Tiny(Vec<TinySnapshot>),
+}
+
+#[derive(Serialize)]
+pub struct Page {
+  page: u64,
+  per_page: u64,
+  has_more: bool,
+  items: RangeItems,
+}
+
+#[get("/range/<from>/<to>?<group>&<page>&<per_page>")]
+pub async fn snapshots_range(
+  from: Result<DateParam, ApiError>,
+  to: Result<DateParam, ApiError>,
+  group: Option<&str>,
+  page: Option<u64>,
+  per_page: Option<u64>,
+  db: &MongoPool,
+) -> Result<Json<Page>, ApiError> {
+  let (from, to) = (from?.0, to?.0);
+  if from > to {
+    return Err(ApiError::InvalidQueryParam(format!("{} > {}", from, to)));
+  }
+
+  let page = page.unwrap_or(1).max(1);
+  let per_page = per_page.unwrap_or(10).clamp(1, 50);
+  let skip = (page - 1)
+    .checked_mul(per_page)
+    .and_then(|skip| i64::try_from(skip).ok())
+    .ok_or_else(|| ApiError::InvalidQueryParam(format!("page={}", page)))?;
+  let mut snapshots = db.range(from, to, group, skip, per_page as i64 + 1).await?;
+  let has_more = snapshots.len() as u64 > per_page;
+  snapshots.truncate(per_page as usize);
+
+  let items = match group {
+    Some(group) => RangeItems::Tiny(snapshots.iter().map(|s| s.tiny(group)).collect()),
+    None => RangeItems::Snapshots(snapshots),
+  };
+  Ok(Json(Page { page, per_page, has_more, items }))
+}
+
 #[get("/uid/<uid>")]
-pub async fn snapshot_by_id(uid: &str, db: &MongoPool, cache: &CachePool) -> Result<Json<Snapshot>, ApiError> {
+pub async fn snapshot_by_id(uid: &str, db: &MongoPool, cache: &CachePool) -> Result<ETagged<Json<Snapshot>>, ApiError> {
+  find_by_uid(uid, db, cache).await.map(tagged)
+}
+
+#[get("/diff/<from>/<to>")]
+pub async fn snapshot_diff(from: &str, to: &str, db: &MongoPool, cache: &CachePool) -> Result<Json<SnapshotDiff>, ApiError> {
+  let from = find_by_uid(from, db, cache).await?;
+  let to = find_by_uid(to, db, cache).await?;
+  Ok(Json(diff::diff(&from, &to)))
+}
+
+#[get("/ical/<group>")]
+pub async fn group_calendar(group: &str, db: &MongoPool, cache: &CachePool) -> Result<(ContentType, String), ApiError> {
+  check_group(group)?;
+  let today = find_latest(Fetch::Today, db, cache).await?;
+  let next = find_latest(Fetch::Next, db, cache).await?;
+  let today_date = now_date();
+  let next_date = next
+    .as_ref()
+    .map(|s| s.date)
+    .unwrap_or_else(|| schedule::next_workday(&today_date));
+
+  let days = [schedule::effective(today.as_ref(), &today_date, group), schedule::effective(next.as_ref(), &next_date, group)];
+  Ok((ContentType::Calendar, ical::calendar(group, &days)))
+}
+
+#[get("/ical/<group>/<date>")]
+pub async fn group_calendar_by_date(
+  group: &str,
+  date: Result<DateParam, ApiError>,
+  db: &MongoPool,
+) -> Result<(ContentType, String), ApiError> {
+  check_group(group)?;
+  let date = date?.0;
+  let snapshot = db.by_date(date).await?;
+  let date = date.to_chrono();
+
+  let days = [schedule::effective(snapshot.as_ref(), &date, group)];
+  Ok((ContentType::Calendar, ical::calendar(group, &days)))
+}
+
+#[get("/effective/<fetch>/<group>")]
+pub async fn latest_effective(
+  fetch: FetchParam,
+  group: &str,
+  db: &MongoPool,
+  cache: &CachePool,
+) -> Result<Json<DaySchedule>, ApiError> {
+  check_group(group)?;
+  let snapshot = find_latest(*fetch, db, cache).await?;
+  let date = fetch_date(snapshot.as_ref(), *fetch);
+
+  Ok(Json(schedule::effective(snapshot.as_ref(), &date, group)))
+}
+
+#[get("/effective/date/<date>/<group>")]
+pub async fn effective_by_date(
+  date: Result<DateParam, ApiError>,
+  group: &str,
+  db: &MongoPool,
+) -> Result<Json<DaySchedule>, ApiError> {
+  check_group(group)?;
+  let date = date?.0;
+  let snapshot = db.by_date(date).await?;
+  Ok(Json(schedule::effective(snapshot.as_ref(), &date.to_chrono(), group)))
+}
+
+#[get("/week/<group>")]
+pub async fn week(group: &str, db: &MongoPool) -> Result<Json<Vec<DaySchedule>>, ApiError> {
+  week_schedule(group, &now_date(), db).await.map(Json)
+}
+
+#[get("/week/<group>/<date>")]
+pub async fn week_by_date(
+  group: &str,
+  date: Result<DateParam, ApiError>,
+  db: &MongoPool,
+) -> Result<Json<Vec<DaySchedule>>, ApiError> {
+  let date = date?.0;
+  week_schedule(group, &date.to_chrono(), db).await.map(Json)
+}
+
+#[get("/rooms/<fetch>")]
+pub async fn latest_rooms(fetch: FetchParam, db: &MongoPool, cache: &CachePool) -> Result<Json<Rooms>, ApiError> {
+  let snapshot = find_latest(*fetch, db, cache).await?;
+  let date = fetch_date(snapshot.as_ref(), *fetch);
+
+  let known = known_classrooms(db).await?;
+  Ok(Json(rooms::rooms(snapshot.as_ref(), &date, known)))
+}
+
+#[get("/rooms/date/<date>")]
+pub async fn rooms_by_date(date: Result<DateParam, ApiError>, db: &MongoPool) -> Result<Json<Rooms>, ApiError> {
+  let date = date?.0;
+  let snapshot = db.by_date(date).await?;
+  let known = known_classrooms(db).await?;
+  Ok(Json(rooms::rooms(snapshot.as_ref(), &date.to_chrono(), known)))
+}
+
+#[get("/metrics")]
+pub async fn prometheus(cache: &CachePool) -> (ContentType, String) {
+  metrics::set("maiq_cached_snapshots", String::new(), cache.read().await.cached_count() as f64);
+  (ContentType::new("text", "plain").with_params(("version", "0.0.4")), metrics::render())
+}
+
+#[get("/cached")]
+pub async fn cached(_secret: ApiKey, cache: &CachePool) -> Result<Json<Vec<Snapshot>>, ApiError> {
+  Ok(Json(cache.read().await.collect_all()))
+}
+
+#[derive(Serialize)]
+pub struct CacheInfo {
+  pub size_limit: usize,
+  pub age_limit_secs: i64,
+  pub entries: Vec<CacheEntry>,
+}
+
+#[derive(Serialize)]
+pub struct Cleared {
+  pub removed: usize,
+}
+
+#[get("/cache")]
+pub async fn cache_info(_secret: ApiKey, cache: &CachePool) -> Json<CacheInfo> {
+  let cache = cache.read().await;
+  Json(CacheInfo {
+    size_limit: env::cache_size(),
+    age_limit_secs: cache.cache_age_limit().num_seconds(),
+    entries: cache.entries(),
+  })
+}
+
+#[delete("/cache/<uid>")]
+pub async fn evict_cached(_secret: ApiKey, uid: &str, cache: &CachePool) -> Result<Status, ApiError> {
+  match cache.write().await.evict(uid) {
+    true => Ok(Status::NoContent),
+    false => Err(ApiError::SnapshotNotFound(uid.into())),
+  }
+}
+
+#[delete("/cache")]
+pub async fn clear_cache(_secret: ApiKey, cache: &CachePool) -> Json<Cleared> {
+  Json(Cleared { removed: cache.write().await.clear() })
+}
+
+/// Drops poll state, so the next update reports changes against nothing
+#[post("/poll/reset")]
+pub async fn reset_poll(_secret: ApiKey, cache: &CachePool) -> Result<Status, ApiError> {
+  cache::CachePool::reset(cache).await?;
+  Ok(Status::NoContent)
+}
+
+/// Runs the update right away, for `fetch` only if given
+#[post("/refresh?<fetch>")]
+pub async fn refresh(_secret: ApiKey, fetch: Option<&str>, cache: &CachePool) -> Result<Json<Vec<Refreshed>>, ApiError> {
+  let fetches = match fetch.map(FetchParam::from_param).transpose()? {
+    Some(fetch) => vec![*fetch],
+    None => vec![Fetch::Today, Fetch::Next],
+  };
+  let refreshed = cache::CachePool::refresh(cache, &fetches).await;
+  cache.read().await.notify_poll();
+  Ok(Json(refreshed))
+}
+
+/// Snapshot uid is a hash of its content, so it makes a strong `ETag`
+fn tagged(snapshot: Snapshot) -> ETagged<Json<Snapshot>> {
+  let tag = snapshot.uid.clone();
+  ETagged::new(Json(snapshot), &tag)
+}
+
+async fn find_by_uid(uid: &str, db: &MongoPool, cache: &CachePool) -> Result<Snapshot, ApiError> {
   if let Ok(Some(s)) = cache.read().await.by_uid(uid).await {
-    return Ok(Json(s));
+    return Ok(s);
   }
+
   info!("Trying to fetch snapshot {} from db", uid);
   match db.by_uid(uid).await? {
     Some(s) => {
       cache.write().await.save(&s).await?;
-      Ok(Json(s))
+      Ok(s)
     }
     None => Err(ApiError::SnapshotNotFound(uid.to_string())),
   }
 }
 
-#[get("/cached")]
-pub async fn cached(_secret: ApiKey, cache: &CachePool) -> Result<Json<Vec<Snapshot>>, ApiError> {
-  Ok(Json(cache.read().await.collect_all()))
+async fn find_latest(fetch: Fetch, db: &MongoPool, cache: &CachePool) -> Result<Option<Snapshot>, ApiError> {
+  if let Ok(Some(s)) = cache.read().await.latest(fetch).await {
+    return Ok(Some(s));
+  }
+
+  info!("Trying to fetch {:?} snapshot from db", fetch);
+  let snapshot = db.latest(fetch).await?;
+  if let Some(s) = snapshot.as_ref() {
+    cache.write().await.save(s).await?;
+  }
+  Ok(snapshot)
+}
+
+async fn known_classrooms(db: &MongoPool) -> Result<BTreeSet<String>, ApiError> {
+  let mut known = rooms::default_classrooms();
+  known.extend(db.classrooms().await?);
+  Ok(known)
+}
+
+async fn week_schedule(group: &str, date: &DateTime<Utc>, db: &MongoPool) -> Result<Vec<DaySchedule>, ApiError> {
+  check_group(group)?;
+  let mut days = vec![];
+  for day in schedule::week_of(date) {
+    let snapshot = db.by_date(day.into()).await?;
+    days.push(schedule::effective(snapshot.as_ref(), &day, group));
+  }
+  Ok(days)
+}
+
+/// Date of the snapshot, or the date it would have if it existed
+fn fetch_date(snapshot: Option<&Snapshot>, fetch: Fetch) -> DateTime<Utc> {
+  match (snapshot, fetch) {
+    (Some(s), _) => s.date,
+    (None, Fetch::Today) => now_date(),
+    (None, Fetch::Next) => schedule::next_workday(&now_date()),
+  }
+}
+
+fn check_group(group: &str) -> Result<(), ApiError> {
+  match maiq_parser::env::groups().iter().any(|g| g == group) {
+    true => Ok(()),
+    false => Err(ApiError::GroupNotFound(group.into())),
+  }
+}
+
+#[derive(Deserialize)]
+pub struct NewWebhook {
+  url: String,
+  secret: String,
+}
+
+#[derive(Serialize)]
+pub struct WebhookInfo {
+  id: String,
+  url: String,
+}
+
+#[get("/webhooks")]
+pub async fn list_webhooks(_secret: ApiKey, db: &MongoPool) -> Result<Json<Vec<WebhookInfo>>, ApiError> {
+  let hooks = db.webhooks().await?;
+  let hooks = hooks
+    .into_iter()
+    .map(|h| WebhookInfo { id: h.id.map(|id| id.to_hex()).unwrap_or_default(), url: h.url })
+    .collect();
+  Ok(Json(hooks))
+}
+
+#[post("/webhooks", data = "<hook>")]
+pub async fn add_webhook(_secret: ApiKey, hook: Json<NewWebhook>, db: &MongoPool) -> Result<Json<WebhookInfo>, ApiError> {
+  let NewWebhook { url, secret } = hook.into_inner();
+  if reqwest::Url::parse(&url).is_err() {
+    return Err(ApiError::InvalidQueryParam(url));
+  }
+
+  let id = db.add_webhook(url.clone(), secret).await?;
+  Ok(Json(WebhookInfo { id: id.map(|id| id.to_hex()).unwrap_or_default(), url }))
+}
+
+#[delete("/webhooks/<id>")]
+pub async fn remove_webhook(_secret: ApiKey, id: &str, db: &MongoPool) -> Result<Status, ApiError> {
+  let oid = ObjectId::parse_str(id).map_err(|_| ApiError::InvalidQueryParam(id.into()))?;
+  match db.remove_webhook(oid).await? {
+    true => Ok(Status::NoContent),
+    false => Err(ApiError::WebhookNotFound(id.into())),
+  }
+}
+
+#[derive(Deserialize)]
+pub struct ManualSnapshot {
+  pub groups: Vec<ManualGroup>,
+}
+
+#[derive(Deserialize)]
+pub struct ManualGroup {
+  pub name: String,
+  pub lessons: Vec<Lesson>,
+}
+
+#[derive(Deserialize)]
+pub struct ManualLessons {
+  pub lessons: Vec<Lesson>,
+}
+
+/// Publishes the snapshot for the date by hand. It wins over parsed ones until released
+#[put("/snapshot/<date>", data = "<snapshot>")]
+pub async fn put_snapshot(
+  _secret: ApiKey,
+  date: Result<DateParam, ApiError>,
+  snapshot: Json<ManualSnapshot>,
+  db: &MongoPool,
+  cache: &CachePool,
+) -> Result<Json<Snapshot>, ApiError> {
+  let date = date?.0;
+  let groups = snapshot.into_inner().groups;
+  for group in groups.iter() {
+    check_group(&group.name)?;
+  }
+
+  let groups = groups.into_iter().map(|g| manual_group(g.name, g.lessons)).collect();
+  publish(manual_snapshot(date, groups), db, cache).await
+}
+
+/// Replaces lessons of the group in the latest snapshot for the date and publishes it as manual.
+/// Empty `lessons` removes the group
+#[put("/snapshot/<date>/<group>", data = "<lessons>")]
+pub async fn put_group(
+  _secret: ApiKey,
+  date: Result<DateParam, ApiError>,
+  group: &str,
+  lessons: Json<ManualLessons>,
+  db: &MongoPool,
+  cache: &CachePool,
+) -> Result<Json<Snapshot>, ApiError> {
+  let date = date?.0;
+  check_group(group)?;
+
+  let mut groups = db.by_date(date).await?.map(|s| s.groups).unwrap_or_default();
+  let lessons = lessons.into_inner().lessons;
+  let index = groups.iter().position(|g| g.name == group);
+  match (index, lessons.is_empty()) {
+    (Some(index), true) => _ = groups.remove(index),
+    (Some(index), false) => groups[index] = manual_group(group.into(), lessons),
+    (None, false) => groups.push(manual_group(group.into(), lessons)),
+    (None, true) => (),
+  }
+
+  publish(manual_snapshot(date, groups), db, cache).await
+}
+
+/// Lets parsed snapshots for the date through again, starting from the next update
+#[delete("/snapshot/<date>/pin")]
+pub async fn release_snapshot(_secret: ApiKey, date: Result<DateParam, ApiError>, db: &MongoPool) -> Result<Status, ApiError> {
+  let date = date?.0;
+  match db.unpin(date).await? {
+    true => Ok(Status::NoContent),
+    false => Err(ApiError::PinNotFound(format!("{}", date))),
+  }
+}
+
+async fn publish(snapshot: Snapshot, db: &MongoPool, cache: &CachePool) -> Result<Json<Snapshot>, ApiError> {
+  db.pin(&snapshot).await?;
+  info!("Snapshot {} for {} published manually", snapshot.uid, snapshot.date);
+
+  let mut cache = cache.write().await;
+  cache.save(&snapshot).await?;
+  cache.publish(snapshot.clone());
+  Ok(Json(snapshot))
+}
+
+fn manual_snapshot(date: mongodb::bson::DateTime, groups: Vec<Group>) -> Snapshot {
+  let date = date.to_chrono();
+  let uids = groups.iter().map(|g| g.uid.as_str()).collect::<Vec<_>>();
+  let uid = content_uid(&(date, uids));
+  Snapshot { uid, date, parsed_date: now(), groups }
+}
+
+fn manual_group(name: String, lessons: Vec<Lesson>) -> Group {
+  let uid = content_uid(&(&name, &lessons));
+  Group { uid, name, lessons }
+}
+
+/// First 10 hex chars of sha256 of the JSON representation, same length as parsed uids
+fn content_uid<T: Serialize>(content: &T) -> String {
+  let mut uid = hex::encode(Sha256::digest(serde_json::to_vec(content).unwrap_or_default()));
+  uid.truncate(10);
+  uid
 }
@@ -0,0 +1,46 @@
+use chrono::{DateTime, Utc};
+use maiq_parser::Snapshot;
+use rocket::{serde::json::Json, Shutdown};
+use serde::Serialize;
+
+use super::{error::ApiError, routes::wait_poll, CachePool};
+
+#[derive(Serialize)]
+pub struct Poll {
+  pub today: Option<PollDay>,
+  pub next: Option<PollDay>,
+  pub next_update: DateTime<Utc>,
+  /// Pass as `since` to wait for the next poll
+  pub token: u64,
+}
+
+#[derive(Serialize)]
+pub struct PollDay {
+  pub uid: String,
+  pub date: DateTime<Utc>,
+  pub changes: Vec<String>,
+}
+
+impl PollDay {
+  fn new(snapshot: Option<Snapshot>, changes: Vec<String>) -> Option<PollDay> {
+    snapshot.map(|s| PollDay { uid: s.uid, date: s.date, changes })
+  }
+}
+
+impl Poll {
+  fn new(poll: maiq_api_wrapper::Poll, token: u64) -> Self {
+    Poll {
+      today: PollDay::new(poll.today_snapshot, poll.today_changes),
+      next: PollDay::new(poll.next_snapshot, poll.next_changes),
+      next_update: poll.next_update,
+      token,
+    }
+  }
+}
+
+/// Same long polling as in v1, but token is in the body
+#[get("/poll?<since>")]
+pub async fn poll_v2(since: Option<u64>, cache: &CachePool, shutdown: Shutdown) -> Result<Json<Poll>, ApiError> {
+  let (poll, token) = wait_poll(since, cache, shutdown).await;
+  Ok(Json(Poll::new(poll, token)))
+}
@@ -1,6 +1,6 @@
 use std::str::FromStr;
 
-use chrono::Duration;
+use chrono::{Datelike, Duration, NaiveDateTime, NaiveTime, Weekday};
 use lazy_static::lazy_static;
 use std::ops::Deref;
 
@@ -64,18 +64,293 @@ pub fn parse_var<T: FromStr + Default>(var: &'static str) -> T {
 }
 
 env_params! {
-  Duration as Secs { |s: &str| s.parse().map(Duration::seconds) }
+  Duration as Secs { |s: &str| s.parse().map(Duration::seconds) },
+  Duration as UpdateRate { |s: &str| s.parse().ok().filter(|secs| *secs > 0).map(Duration::seconds).ok_or(()) },
+  Duration as UpdateAgeLimit { |s: &str| s.parse().map(Duration::seconds) },
+  Duration as UpdateGrace { |s: &str| s.parse().map(Duration::seconds) },
+  Duration as BackoffLimit { |s: &str| s.parse().map(Duration::seconds) },
+  Duration as LongPollTimeout { |s: &str| s.parse().map(Duration::seconds) }
 }
 
 env_default! {
-  Secs => Duration::seconds(10)
+  Secs => Duration::seconds(10),
+  UpdateRate => Duration::seconds(60),
+  UpdateAgeLimit => Duration::minutes(15),
+  UpdateGrace => Duration::minutes(5),
+  BackoffLimit => Duration::hours(1),
+  LongPollTimeout => Duration::seconds(30)
+}
+
+#[derive(Debug, Clone)]
+pub struct LessonTimes(Vec<(NaiveTime, NaiveTime)>);
+
+impl LessonTimes {
+  pub fn get(&self, num: u8) -> Option<(NaiveTime, NaiveTime)> {
+    self.0.get((num as usize).checked_sub(1)?).copied()
+  }
+}
+
+impl FromStr for LessonTimes {
+  type Err = ();
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let parse = |x: &str| NaiveTime::parse_from_str(x.trim(), "%H:%M").map_err(|_| ());
+    s.split(',')
+      .map(|range| {
+        let (start, end) = range.split_once('-').ok_or(())?;
+        Ok((parse(start)?, parse(end)?))
+      })
+      .collect::<Result<_, _>>()
+      .map(LessonTimes)
+  }
+}
+
+impl Default for LessonTimes {
+  fn default() -> Self {
+    "08:30-10:00,10:10-11:40,12:10-13:40,13:50-15:20,15:30-17:00,17:10-18:40,18:50-20:20"
+      .parse()
+      .unwrap()
+  }
+}
+
+/// Comma separated `<url>|<secret>` pairs
+#[derive(Debug, Clone, Default)]
+pub struct Webhooks(Vec<(String, String)>);
+
+impl Deref for Webhooks {
+  type Target = Vec<(String, String)>;
+  fn deref(&self) -> &Self::Target {
+    &self.0
+  }
+}
+
+impl FromStr for Webhooks {
+  type Err = ();
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    s.split(',')
+      .filter(|hook| !hook.trim().is_empty())
+      .map(|hook| {
+        let (url, secret) = hook.split_once('|').ok_or(())?;
+        Ok((url.trim().into(), secret.trim().into()))
+      })
+      .collect::<Result<_, _>>()
+      .map(Webhooks)
+  }
+}
+
+/// Update rate window, `[<day>[-<day>]] [HH:MM-HH:MM]=<secs|off>`
+#[derive(Debug, Clone)]
+pub struct UpdateWindow {
+  days: Option<(Weekday, Weekday)>,
+  time: Option<(NaiveTime, NaiveTime)>,
+  rate: Option<Duration>,
+}
+
+impl UpdateWindow {
+  fn contains(&self, at: NaiveDateTime) -> bool {
+    let in_range = |from: u32, to: u32, x: u32| match from <= to {
+      true => from <= x && x <= to,
+      false => x >= from || x <= to,
+    };
+    let day = |d: Weekday| d.num_days_from_monday();
+    let days = match self.days {
+      Some((from, to)) => in_range(day(from), day(to), day(at.weekday())),
+      None => true,
+    };
+    let time = match self.time {
+      Some((from, to)) if from <= to => from <= at.time() && at.time() < to,
+      Some((from, to)) => at.time() >= from || at.time() < to,
+      None => true,
+    };
+    days && time
+  }
+}
+
+impl FromStr for UpdateWindow {
+  type Err = ();
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let (spec, rate) = s.rsplit_once('=').ok_or(())?;
+    let rate = match rate.trim() {
+      "off" => None,
+      secs => Some(Duration::seconds(secs.parse().ok().filter(|secs| *secs > 0).ok_or(())?)),
+    };
+
+    let parse_time = |x: &str| NaiveTime::parse_from_str(x, "%H:%M").map_err(|_| ());
+    let (mut days, mut time) = (None, None);
+    for part in spec.split_whitespace() {
+      let (from, to) = part.split_once('-').unwrap_or((part, part));
+      match part.contains(':') {
+        true => time = Some((parse_time(from)?, parse_time(to)?)),
+        false => days = Some((from.parse().map_err(|_| ())?, to.parse().map_err(|_| ())?)),
+      }
+    }
+
+    Ok(UpdateWindow { days, time, rate })
+  }
+}
+
+/// Windows are checked in order, the first one containing the time wins.
+/// Time outside of any window uses `UPDATE_CACHE_INTERVAL_SECS`
+#[derive(Debug, Clone, Default)]
+pub struct UpdateSchedule(Vec<UpdateWindow>);
+
+impl UpdateSchedule {
+  /// `None` means updates are off at this time
+  pub fn rate_at(&self, at: NaiveDateTime) -> Option<Duration> {
+    match self.0.iter().find(|w| w.contains(at)) {
+      Some(window) => window.rate,
+      None => Some(*update_rate()),
+    }
+  }
+
+  /// First moment after `last` which is at least the current rate away from it and isn't in an `off` window
+  pub fn next_run(&self, last: NaiveDateTime) -> NaiveDateTime {
+    let limit = last + Duration::days(8);
+    let mut at = last;
+    while at < limit {
+      at = match self.rate_at(at) {
+        Some(rate) if at - last >= rate => return at,
+        Some(rate) => (last + rate).min(self.next_boundary(at)),
+        None => self.next_boundary(at),
+      };
+    }
+    last + *update_rate()
+  }
+
+  /// Next moment after `at` when another window may start to apply
+  fn next_boundary(&self, at: NaiveDateTime) -> NaiveDateTime {
+    let midnight = at.date().succ_opt().unwrap().and_time(NaiveTime::MIN);
+    self
+      .0
+      .iter()
+      .filter_map(|w| w.time)
+      .flat_map(|(from, to)| [from, to])
+      .map(|time| at.date().and_time(time))
+      .filter(|time| *time > at)
+      .fold(midnight, NaiveDateTime::min)
+  }
+}
+
+impl FromStr for UpdateSchedule {
+  type Err = ();
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    s.split(',')
+      .filter(|window| !window.trim().is_empty())
+      .map(str::parse)
+      .collect::<Result<_, _>>()
+      .map(UpdateSchedule)
+  }
 }
 
 vars! {
-  update_rate (UPDATE_CACHE_INTERVAL_SECS) -> u64,
+  update_rate (UPDATE_CACHE_INTERVAL_SECS) -> UpdateRate,
   cache_size (CACHE_SIZE) -> usize,
   cache_age_limit (CACHE_AGE_LIMIT_SECS) -> Secs,
   db_url (DATABASE_CONNECTION_URL) -> String,
   db_default_collection (DEFAULT_DATABASE_NAME) -> String,
-  api_secret (API_SECRET) -> String
+  api_secret (API_SECRET) -> String,
+  lesson_times (LESSON_TIMES) -> LessonTimes,
+  webhooks (WEBHOOKS) -> Webhooks,
+  update_age_limit (READINESS_UPDATE_AGE_LIMIT_SECS) -> UpdateAgeLimit,
+  update_grace (READINESS_UPDATE_GRACE_SECS) -> UpdateGrace,
+  update_backoff_limit (UPDATE_BACKOFF_LIMIT_SECS) -> BackoffLimit,
+  update_schedule (UPDATE_SCHEDULE) -> UpdateSchedule,
+  long_poll_timeout (LONG_POLL_TIMEOUT_SECS) -> LongPollTimeout
+}
+
+#[cfg(test)]
+mod tests {
+  use chrono::NaiveDate;
+
+  use super::*;
+
+  /// 16.01.2023 is a monday
+  fn at(day: u32, time: &str) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2023, 1, day)
+      .unwrap()
+      .and_time(NaiveTime::parse_from_str(time, "%H:%M").unwrap())
+  }
+
+  fn schedule(s: &str) -> UpdateSchedule {
+    s.parse().unwrap()
+  }
+
+  #[test]
+  fn parses_window() {
+    let window = "mon-fri 07:00-09:00=60".parse::<UpdateWindow>().unwrap();
+    assert_eq!(window.days, Some((Weekday::Mon, Weekday::Fri)));
+    assert_eq!(window.time.map(|(from, to)| (from.to_string(), to.to_string())), Some(("07:00:00".into(), "09:00:00".into())));
+    assert_eq!(window.rate, Some(Duration::seconds(60)));
+
+    let window = "sun=off".parse::<UpdateWindow>().unwrap();
+    assert_eq!(window.days, Some((Weekday::Sun, Weekday::Sun)));
+    assert!(window.time.is_none() && window.rate.is_none());
+
+    let window = "=900".parse::<UpdateWindow>().unwrap();
+    assert!(window.days.is_none() && window.time.is_none());
+  }
+
+  #[test]
+  fn rejects_invalid_windows() {
+    for s in ["07:00-09:00", "07:00-09:00=0", "07:00-09:00=-5", "mon=abc", "foo=60", "7-9=60", "07:00-25:00=60"] {
+      assert!(s.parse::<UpdateWindow>().is_err(), "{} parsed", s);
+    }
+    assert!("mon=60,,".parse::<UpdateSchedule>().is_ok());
+    assert!("mon=60,tue=0".parse::<UpdateSchedule>().is_err());
+  }
+
+  #[test]
+  fn time_range_wraps_around_midnight() {
+    let window = "22:00-06:00=900".parse::<UpdateWindow>().unwrap();
+    assert!(window.contains(at(16, "23:00")));
+    assert!(window.contains(at(17, "05:59")));
+    assert!(!window.contains(at(17, "06:00")));
+    assert!(!window.contains(at(16, "12:00")));
+  }
+
+  #[test]
+  fn day_range_wraps_around_week() {
+    let window = "fri-mon=60".parse::<UpdateWindow>().unwrap();
+    for day in [20, 21, 22, 16] {
+      assert!(window.contains(at(day, "12:00")), "{} isn't in fri-mon", day);
+    }
+    for day in [17, 18, 19] {
+      assert!(!window.contains(at(day, "12:00")), "{} is in fri-mon", day);
+    }
+  }
+
+  #[test]
+  fn first_matching_window_wins() {
+    let schedule = schedule("mon 07:00-09:00=60,07:00-09:00=120,=900");
+    assert_eq!(schedule.rate_at(at(16, "08:00")), Some(Duration::seconds(60)));
+    assert_eq!(schedule.rate_at(at(17, "08:00")), Some(Duration::seconds(120)));
+    assert_eq!(schedule.rate_at(at(17, "10:00")), Some(Duration::seconds(900)));
+  }
+
+  #[test]
+  fn next_run_uses_rate_in_effect() {
+    let schedule = schedule("07:00-09:00=60,=900");
+    assert_eq!(schedule.next_run(at(16, "08:00")), at(16, "08:01"));
+    assert_eq!(schedule.next_run(at(16, "10:00")), at(16, "10:15"));
+  }
+
+  #[test]
+  fn next_run_crosses_window_boundary() {
+    let schedule = schedule("07:00-09:00=60,=900");
+    // Slow rate would run at 07:05, but the fast window starts earlier
+    assert_eq!(schedule.next_run(at(16, "06:50")), at(16, "07:00"));
+    // Fast rate ends at 09:00, slow rate is counted from the last run
+    assert_eq!(schedule.next_run(at(16, "08:59")), at(16, "09:14"));
+  }
+
+  #[test]
+  fn next_run_skips_off_windows() {
+    let schedule = schedule("sun=off,00:00-07:00=off,=600");
+    assert_eq!(schedule.rate_at(at(22, "12:00")), None);
+    assert_eq!(schedule.next_run(at(21, "23:55")), at(23, "07:00"));
+    assert_eq!(schedule.next_run(at(17, "06:00")), at(17, "07:00"));
+  }
 }
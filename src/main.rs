@@ -6,13 +6,18 @@ extern crate log;
 
 mod api;
 mod env;
+mod metrics;
+mod schedule;
 mod storage;
+mod webhooks;
+mod websocket;
 
 use std::sync::Arc;
 
 use api::{
   error::{internal_server_error, not_found, unauthorized},
-  routes::*,
+  openapi,
+  routes::{index, prometheus},
 };
 
 use chrono::NaiveTime;
@@ -36,16 +41,22 @@ async fn main() {
 
   let mongo = MongoPool::init().await.expect("Error while connecting to database");
   let cache = CachePool::new(mongo.clone()).await;
-  cache.write().await.update_tick().await;
+  webhooks::startup_webhook_sender(cache.read().await.subscribe(), mongo.clone());
+  CachePool::update_tick(&cache).await;
 
   startup_cache_updater(cache.clone());
 
   _ = rocket::build()
     .register("/", catchers![not_found, internal_server_error, unauthorized])
-    .mount("/", routes![index])
-    .mount("/api", routes![index, latest, latest_group, poll, snapshot_by_date, snapshot_by_id, default, groups])
-    .mount("/api/dev", routes![cached])
+    .mount("/", routes![index, prometheus])
+    .mount("/api", api::v1())
+    .mount("/api/v1", api::v1())
+    .mount("/api/v2", api::v2())
+    .mount("/api/dev", api::dev())
+    .mount("/health", api::health())
     .attach(Cors)
+    .attach(metrics::RequestMetrics)
+    .attach(openapi::fairing())
     .manage(mongo)
     .manage(cache)
     .launch()
@@ -66,6 +77,7 @@ impl Fairing for Cors {
     response.set_header(Header::new("Access-Control-Allow-Methods", "GET"));
     response.set_header(Header::new("Access-Control-Allow-Headers", "*"));
     response.set_header(Header::new("Access-Control-Allow-Credentials", "true"));
+    response.set_header(Header::new("Access-Control-Expose-Headers", "X-Poll-Token, ETag"));
   }
 }
 
@@ -84,7 +96,9 @@ pub fn startup_cache_updater(cache: Arc<RwLock<CachePool>>) {
 
         info!("Waiting for {}s to drop previous day poll", wait_s);
         tokio::time::sleep(std::time::Duration::from_secs(wait_s as u64)).await;
-        cache_ref.write().await.reset();
+        if let Err(err) = CachePool::reset(&cache_ref).await {
+          warn!("Can't drop saved poll state: {}", err);
+        }
       }
     });
 
@@ -92,12 +106,11 @@ pub fn startup_cache_updater(cache: Arc<RwLock<CachePool>>) {
       let cache_ref = cache.clone();
 
       _ = tokio::spawn(async move {
-        let mut interval = storage::cache::interval();
-        interval.tick().await;
         loop {
-          info!("Sleeping for {:?}", interval.period());
-          interval.tick().await;
-          cache_ref.write().await.update_tick().await;
+          let delay = cache_ref.read().await.next_run() - utils::time::now();
+          info!("Sleeping for {}s", delay.num_seconds());
+          tokio::time::sleep(delay.to_std().unwrap_or_default()).await;
+          CachePool::update_tick(&cache_ref).await;
         }
       })
       .await;
@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use lazy_static::lazy_static;
+use rocket::{
+  fairing::{Fairing, Info, Kind},
+  Data, Request, Response,
+};
+
+const BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+const METRICS: [(&str, &str, &str); 8] = [
+  ("maiq_http_request_duration_seconds", "histogram", "Time spent handling HTTP requests"),
+  ("maiq_cache_requests_total", "counter", "Cache lookups by result"),
+  ("maiq_cached_snapshots", "gauge", "Snapshots currently held in cache"),
+  ("maiq_update_tick_duration_seconds", "histogram", "Duration of cache update ticks by result"),
+  ("maiq_update_last_success_timestamp_seconds", "gauge", "Unix time of the last successful update tick"),
+  ("maiq_parsed_snapshots_total", "counter", "Snapshots parsed from remote"),
+  ("maiq_parser_failures_total", "counter", "Failed attempts to parse snapshot from remote"),
+  ("maiq_mongo_query_duration_seconds", "histogram", "Duration of database queries"),
+];
+
+lazy_static! {
+  static ref REGISTRY: Mutex<Registry> = Mutex::new(Registry::default());
+}
+
+#[derive(Default)]
+struct Registry {
+  values: BTreeMap<(&'static str, String), f64>,
+  histograms: BTreeMap<(&'static str, String), Histogram>,
+}
+
+#[derive(Default)]
+struct Histogram {
+  buckets: [u64; BUCKETS.len()],
+  sum: f64,
+  count: u64,
+}
+
+impl Histogram {
+  fn observe(&mut self, value: f64) {
+    for (bucket, le) in self.buckets.iter_mut().zip(BUCKETS) {
+      if value <= le {
+        *bucket += 1;
+      }
+    }
+    self.sum += value;
+    self.count += 1;
+  }
+
+  fn render(&self, out: &mut String, name: &str, labels: &str) {
+    let sep = if labels.is_empty() { "" } else { "," };
+    for (count, le) in self.buckets.iter().zip(BUCKETS) {
+      _ = writeln!(out, "{}_bucket{{{}{}le=\"{}\"}} {}", name, labels, sep, le, count);
+    }
+    _ = writeln!(out, "{}_bucket{{{}{}le=\"+Inf\"}} {}", name, labels, sep, self.count);
+    _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, self.sum);
+    _ = writeln!(out, "{}_count{{{}}} {}", name, labels, self.count);
+  }
+}
+
+/// Drop guard observing the time it lived into the histogram
+pub struct Timer {
+  name: &'static str,
+  labels: String,
+  start: Instant,
+}
+
+impl Drop for Timer {
+  fn drop(&mut self) {
+    observe(self.name, std::mem::take(&mut self.labels), self.start.elapsed().as_secs_f64());
+  }
+}
+
+pub fn labels(pairs: &[(&str, &str)]) -> String {
+  pairs
+    .iter()
+    .map(|(k, v)| format!("{}=\"{}\"", k, v.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")))
+    .collect::<Vec<_>>()
+    .join(",")
+}
+
+pub fn inc(name: &'static str, labels: String) {
+  *REGISTRY.lock().unwrap().values.entry((name, labels)).or_default() += 1.0;
+}
+
+pub fn set(name: &'static str, labels: String, value: f64) {
+  REGISTRY.lock().unwrap().values.insert((name, labels), value);
+}
+
+pub fn observe(name: &'static str, labels: String, value: f64) {
+  REGISTRY
+    .lock()
+    .unwrap()
+    .histograms
+    .entry((name, labels))
+    .or_default()
+    .observe(value);
+}
+
+pub fn timer(name: &'static str, labels: String) -> Timer {
+  Timer { name, labels, start: Instant::now() }
+}
+
+pub fn mongo_timer(op: &str) -> Timer {
+  timer("maiq_mongo_query_duration_seconds", labels(&[("op", op)]))
+}
+
+pub fn cache_lookup(op: &str, hit: bool) {
+  inc("maiq_cache_requests_total", labels(&[("op", op), ("result", if hit { "hit" } else { "miss" })]));
+}
+
+/// Prometheus text exposition of everything recorded so far
+pub fn render() -> String {
+  let registry = REGISTRY.lock().unwrap();
+  let mut out = String::new();
+  for (name, kind, help) in METRICS {
+    _ = writeln!(out, "# HELP {} {}", name, help);
+    _ = writeln!(out, "# TYPE {} {}", name, kind);
+    for ((_, labels), value) in registry.values.iter().filter(|((n, _), _)| *n == name) {
+      _ = writeln!(out, "{}{{{}}} {}", name, labels, value);
+    }
+    for ((_, labels), histogram) in registry.histograms.iter().filter(|((n, _), _)| *n == name) {
+      histogram.render(&mut out, name, labels);
+    }
+  }
+  out
+}
+
+pub struct RequestMetrics;
+
+struct RequestStart(Instant);
+
+#[rocket::async_trait]
+impl Fairing for RequestMetrics {
+  fn info(&self) -> Info {
+    Info { name: "Request metrics", kind: Kind::Request | Kind::Response }
+  }
+
+  async fn on_request(&self, req: &mut Request<'_>, _: &mut Data<'_>) {
+    req.local_cache(|| RequestStart(Instant::now()));
+  }
+
+  async fn on_response<'r>(&self, req: &'r Request<'_>, res: &mut Response<'r>) {
+    let start = req.local_cache(|| RequestStart(Instant::now()));
+    let route = req.route().map(|r| r.uri.as_str()).unwrap_or("unmatched");
+    let status = res.status().code.to_string();
+    let labels = labels(&[("method", req.method().as_str()), ("route", route), ("status", &status)]);
+    observe("maiq_http_request_duration_seconds", labels, start.0.elapsed().as_secs_f64());
+  }
+}
@@ -0,0 +1,167 @@
+use maiq_api_wrapper::{Change, LessonChange};
+use maiq_parser::{Group, Lesson, Snapshot};
+use serde::Serialize;
+
+pub use maiq_api_wrapper::GroupDiff;
+
+#[derive(Serialize)]
+pub struct SnapshotDiff {
+  pub from: String,
+  pub to: String,
+  pub groups: Vec<GroupDiff>,
+}
+
+/// Lessons changed in place, only differing fields are set
+fn lesson_change(from: &Lesson, to: &Lesson) -> Option<LessonChange> {
+  let lesson = LessonChange {
+    num: to.num,
+    subgroup: to.subgroup,
+    name: change(&from.name, &to.name),
+    teacher: change(&from.teacher, &to.teacher),
+    classroom: change(&from.classroom, &to.classroom),
+  };
+
+  (lesson.name.is_some() || lesson.teacher.is_some() || lesson.classroom.is_some()).then_some(lesson)
+}
+
+fn change<T: PartialEq + Clone>(from: &T, to: &T) -> Option<Change<T>> {
+  (from != to).then(|| Change { from: from.clone(), to: to.clone() })
+}
+
+pub fn diff(from: &Snapshot, to: &Snapshot) -> SnapshotDiff {
+  let mut names = to.groups.iter().map(|g| g.name.as_str()).collect::<Vec<_>>();
+  for group in from.groups.iter() {
+    if !names.contains(&group.name.as_str()) {
+      names.push(group.name.as_str());
+    }
+  }
+
+  let groups = names
+    .into_iter()
+    .filter_map(|name| diff_group(name, find_group(from, name), find_group(to, name)))
+    .collect();
+
+  SnapshotDiff { from: from.uid.clone(), to: to.uid.clone(), groups }
+}
+
+/// Lessons are matched by their number and subgroup, so a lesson of one subgroup is never compared with another's.
+/// Lessons with the same number and subgroup are matched by their order
+pub fn diff_group(name: &str, from: Option<&Group>, to: Option<&Group>) -> Option<GroupDiff> {
+  if let (Some(from), Some(to)) = (from, to) {
+    if from.uid == to.uid {
+      return None;
+    }
+  }
+
+  let from = keyed(from.map(|g| g.lessons.as_slice()).unwrap_or_default());
+  let to = keyed(to.map(|g| g.lessons.as_slice()).unwrap_or_default());
+  let mut diff = GroupDiff { name: name.into(), added: vec![], removed: vec![], changed: vec![] };
+
+  for (key, lesson) in to.iter() {
+    match from.iter().find(|(k, _)| k == key) {
+      Some((_, prev)) => diff.changed.extend(lesson_change(prev, lesson)),
+      None => diff.added.push((*lesson).clone()),
+    }
+  }
+
+  for (key, lesson) in from.iter() {
+    if !to.iter().any(|(k, _)| k == key) {
+      diff.removed.push((*lesson).clone());
+    }
+  }
+
+  (!diff.is_empty()).then_some(diff)
+}
+
+fn find_group<'a>(snapshot: &'a Snapshot, name: &str) -> Option<&'a Group> {
+  snapshot.groups.iter().find(|g| g.name == name)
+}
+
+/// Number, subgroup and order among lessons with both of them the same
+type LessonKey = (u8, Option<u8>, usize);
+
+fn keyed(lessons: &[Lesson]) -> Vec<(LessonKey, &Lesson)> {
+  let same = |a: &Lesson, b: &Lesson| a.num == b.num && a.subgroup == b.subgroup;
+  lessons
+    .iter()
+    .enumerate()
+    .map(|(i, lesson)| ((lesson.num, lesson.subgroup, lessons[..i].iter().filter(|l| same(l, lesson)).count()), lesson))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn lesson(num: u8, subgroup: Option<u8>, teacher: &str) -> Lesson {
+    Lesson { num, subgroup, name: "Математика".into(), teacher: Some(teacher.into()), classroom: Some("101".into()) }
+  }
+
+  fn group(uid: &str, lessons: Vec<Lesson>) -> Group {
+    Group { uid: uid.into(), name: "Ир3-21".into(), lessons }
+  }
+
+  fn diff_lessons(from: Vec<Lesson>, to: Vec<Lesson>) -> Option<GroupDiff> {
+    diff_group("Ир3-21", Some(&group("a", from)), Some(&group("b", to)))
+  }
+
+  #[test]
+  fn same_uid_has_no_diff() {
+    let g = group("a", vec![lesson(1, None, "Иванов")]);
+    assert!(diff_group("Ир3-21", Some(&g), Some(&g)).is_none());
+  }
+
+  #[test]
+  fn removed_subgroup_lesson_isnt_reported_as_changed() {
+    let diff =
+      diff_lessons(vec![lesson(2, Some(1), "Иванов"), lesson(2, Some(2), "Петрова")], vec![lesson(2, Some(2), "Петрова")])
+        .unwrap();
+    assert!(diff.changed.is_empty());
+    assert!(diff.added.is_empty());
+    assert_eq!(diff.removed, vec![lesson(2, Some(1), "Иванов")]);
+  }
+
+  #[test]
+  fn added_subgroup_lesson() {
+    let diff =
+      diff_lessons(vec![lesson(2, Some(2), "Петрова")], vec![lesson(2, Some(1), "Иванов"), lesson(2, Some(2), "Петрова")])
+        .unwrap();
+    assert!(diff.changed.is_empty() && diff.removed.is_empty());
+    assert_eq!(diff.added, vec![lesson(2, Some(1), "Иванов")]);
+  }
+
+  #[test]
+  fn change_keeps_subgroup() {
+    let diff = diff_lessons(
+      vec![lesson(3, Some(1), "Иванов"), lesson(3, Some(2), "Петрова")],
+      vec![lesson(3, Some(1), "Иванов"), lesson(3, Some(2), "Сидоров")],
+    )
+    .unwrap();
+    assert!(diff.added.is_empty() && diff.removed.is_empty());
+    assert_eq!(diff.changed.len(), 1);
+    let change = &diff.changed[0];
+    assert_eq!((change.num, change.subgroup), (3, Some(2)));
+    assert_eq!(
+      change.teacher.as_ref().map(|c| (c.from.clone(), c.to.clone())),
+      Some((Some("Петрова".into()), Some("Сидоров".into())))
+    );
+    assert!(change.name.is_none() && change.classroom.is_none());
+  }
+
+  #[test]
+  fn whole_group_lesson_split_into_subgroups() {
+    let diff =
+      diff_lessons(vec![lesson(1, None, "Иванов")], vec![lesson(1, Some(1), "Иванов"), lesson(1, Some(2), "Петрова")]).unwrap();
+    assert!(diff.changed.is_empty());
+    assert_eq!(diff.removed, vec![lesson(1, None, "Иванов")]);
+    assert_eq!(diff.added.len(), 2);
+  }
+
+  #[test]
+  fn missing_group_is_all_added_or_removed() {
+    let g = group("a", vec![lesson(1, None, "Иванов")]);
+    assert_eq!(diff_group("Ир3-21", None, Some(&g)).unwrap().added.len(), 1);
+    assert_eq!(diff_group("Ир3-21", Some(&g), None).unwrap().removed.len(), 1);
+    assert!(diff_group("Ир3-21", None, None).is_none());
+  }
+}
@@ -0,0 +1,73 @@
+use chrono::{NaiveDate, NaiveTime, Utc};
+
+use crate::env;
+
+use super::DaySchedule;
+
+/// Renders RFC 5545 calendar with an event per lesson.
+/// Lesson times are floating (without timezone), as all the dates we have are local ones
+pub fn calendar(group: &str, days: &[DaySchedule]) -> String {
+  let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+  let times = env::lesson_times();
+  let mut lines = vec![
+    "BEGIN:VCALENDAR".to_string(),
+    "VERSION:2.0".into(),
+    format!("PRODID:-//maiq//maiq-web {}//RU", env!("CARGO_PKG_VERSION")),
+    "CALSCALE:GREGORIAN".into(),
+    "METHOD:PUBLISH".into(),
+    format!("X-WR-CALNAME:{}", escape(group)),
+  ];
+
+  for day in days {
+    let date = day.date.date_naive();
+    for (i, lesson) in day.lessons().iter().enumerate() {
+      let Some((start, end)) = times.get(lesson.num) else {
+        continue;
+      };
+
+      lines.push("BEGIN:VEVENT".into());
+      lines.push(format!("UID:{}-{}-{}-{}@maiq-web", date.format("%Y%m%d"), lesson.num, i, escape(group)));
+      lines.push(format!("DTSTAMP:{}", stamp));
+      lines.push(format!("DTSTART:{}", local(date, start)));
+      lines.push(format!("DTEND:{}", local(date, end)));
+      lines.push(format!("SUMMARY:{}", escape(lesson.name)));
+      if let Some(classroom) = lesson.classroom {
+        lines.push(format!("LOCATION:{}", escape(classroom)));
+      }
+      if let Some(teacher) = lesson.teacher {
+        lines.push(format!("DESCRIPTION:{}", escape(teacher)));
+      }
+      lines.push("END:VEVENT".into());
+    }
+  }
+
+  lines.push("END:VCALENDAR".into());
+  lines.iter().map(|line| fold(line) + "\r\n").collect()
+}
+
+fn local(date: NaiveDate, time: NaiveTime) -> String {
+  date.and_time(time).format("%Y%m%dT%H%M%S").to_string()
+}
+
+fn escape(text: &str) -> String {
+  text
+    .replace('\\', "\\\\")
+    .replace(';', "\\;")
+    .replace(',', "\\,")
+    .replace('\n', "\\n")
+}
+
+/// Content lines must not be longer than 75 octets, so the rest is moved to continuation lines
+fn fold(line: &str) -> String {
+  let mut res = String::with_capacity(line.len());
+  let mut len = 0;
+  for c in line.chars() {
+    if len + c.len_utf8() > 75 {
+      res.push_str("\r\n ");
+      len = 1;
+    }
+    len += c.len_utf8();
+    res.push(c);
+  }
+  res
+}
@@ -0,0 +1,108 @@
+pub mod diff;
+pub mod ical;
+pub mod rooms;
+pub mod teacher;
+
+use chrono::{DateTime, Datelike, Duration, Utc, Weekday};
+use maiq_parser::{default::DefaultLesson, Lesson, Snapshot};
+use serde::Serialize;
+
+pub struct LessonRef<'a> {
+  pub num: u8,
+  pub name: &'a str,
+  pub teacher: Option<&'a str>,
+  pub classroom: Option<&'a str>,
+}
+
+impl<'a> From<&'a Lesson> for LessonRef<'a> {
+  fn from(l: &'a Lesson) -> Self {
+    LessonRef { num: l.num, name: &l.name, teacher: l.teacher.as_deref(), classroom: l.classroom.as_deref() }
+  }
+}
+
+impl<'a> From<&'a DefaultLesson> for LessonRef<'a> {
+  fn from(l: &'a DefaultLesson) -> Self {
+    LessonRef { num: l.num, name: &l.name, teacher: l.teacher.as_deref(), classroom: l.classroom.as_deref() }
+  }
+}
+
+/// Even (numerator) weeks are the ones with an even ISO week number
+pub fn is_even_week(date: &DateTime<Utc>) -> bool {
+  date.iso_week().week() & 1 == 0
+}
+
+pub fn next_workday(date: &DateTime<Utc>) -> DateTime<Utc> {
+  let next = *date + Duration::days(1);
+  match next.weekday() {
+    Weekday::Sun => next + Duration::days(1),
+    _ => next,
+  }
+}
+
+/// Monday to Saturday of the week the date belongs to
+pub fn week_of(date: &DateTime<Utc>) -> Vec<DateTime<Utc>> {
+  let monday = *date - Duration::days(date.weekday().num_days_from_monday() as i64);
+  (0..6).map(|i| monday + Duration::days(i)).collect()
+}
+
+pub fn default_lessons(date: &DateTime<Utc>, group: &str) -> Option<Vec<&'static DefaultLesson>> {
+  let even = is_even_week(date);
+  let default = maiq_parser::default_for(date.weekday(), group)?;
+  Some(
+    default
+      .lessons
+      .iter()
+      .filter(|l| l.is_even.unwrap_or(even) == even)
+      .collect(),
+  )
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+  Snapshot,
+  Default,
+  None,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum Lessons {
+  Snapshot(Vec<Lesson>),
+  Default(Vec<&'static DefaultLesson>),
+}
+
+/// Lessons the group actually has on the date
+#[derive(Serialize)]
+pub struct DaySchedule {
+  pub date: DateTime<Utc>,
+  pub group: String,
+  pub source: Source,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub uid: Option<String>,
+  pub lessons: Lessons,
+}
+
+impl DaySchedule {
+  pub fn lessons(&self) -> Vec<LessonRef<'_>> {
+    match &self.lessons {
+      Lessons::Snapshot(lessons) => lessons.iter().map(Into::into).collect(),
+      Lessons::Default(lessons) => lessons.iter().map(|l| (*l).into()).collect(),
+    }
+  }
+}
+
+/// Takes lessons of the group from the snapshot. Snapshots only contain groups with changes,
+/// so if the group isn't there, it's default schedule for the weekday resolved against week parity
+pub fn effective(snapshot: Option<&Snapshot>, date: &DateTime<Utc>, group: &str) -> DaySchedule {
+  let uid = snapshot.map(|s| s.uid.clone());
+  let (source, lessons) = match snapshot.and_then(|s| s.groups.iter().find(|g| g.name == group)) {
+    Some(g) => (Source::Snapshot, Lessons::Snapshot(g.lessons.clone())),
+    None => match default_lessons(date, group) {
+      Some(lessons) => (Source::Default, Lessons::Default(lessons)),
+      None => (Source::None, Lessons::Default(vec![])),
+    },
+  };
+
+  DaySchedule { date: *date, group: group.into(), source, uid, lessons }
+}
@@ -0,0 +1,83 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{DateTime, Utc, Weekday};
+use maiq_parser::Snapshot;
+use serde::Serialize;
+
+use super::effective;
+
+#[derive(Serialize)]
+pub struct Rooms {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub uid: Option<String>,
+  pub date: DateTime<Utc>,
+  pub lessons: Vec<RoomsAtLesson>,
+}
+
+#[derive(Serialize)]
+pub struct RoomsAtLesson {
+  pub num: u8,
+  pub occupied: Vec<OccupiedRoom>,
+  pub free: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct OccupiedRoom {
+  pub classroom: String,
+  pub group: String,
+  pub name: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub teacher: Option<String>,
+}
+
+const WEEKDAYS: [Weekday; 6] = [Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri, Weekday::Sat];
+
+/// Every classroom mentioned in default schedules of all groups
+pub fn default_classrooms() -> BTreeSet<String> {
+  maiq_parser::env::groups()
+    .iter()
+    .flat_map(|group| {
+      WEEKDAYS
+        .iter()
+        .filter_map(move |day| maiq_parser::default_for(*day, group))
+    })
+    .flat_map(|default| default.lessons.iter().filter_map(|l| l.classroom.clone()))
+    .collect()
+}
+
+/// Occupancy is built from lessons of every group for the day, so groups missing in the snapshot are taken from defaults
+pub fn rooms(snapshot: Option<&Snapshot>, date: &DateTime<Utc>, mut known: BTreeSet<String>) -> Rooms {
+  let mut occupied = BTreeMap::<u8, Vec<OccupiedRoom>>::new();
+  for group in maiq_parser::env::groups() {
+    let day = effective(snapshot, date, group);
+    for lesson in day.lessons() {
+      let Some(classroom) = lesson.classroom else {
+        continue;
+      };
+
+      known.insert(classroom.into());
+      occupied.entry(lesson.num).or_default().push(OccupiedRoom {
+        classroom: classroom.into(),
+        group: group.clone(),
+        name: lesson.name.into(),
+        teacher: lesson.teacher.map(Into::into),
+      });
+    }
+  }
+
+  let last = occupied.keys().next_back().copied().unwrap_or_default();
+  let lessons = (1..=last)
+    .map(|num| {
+      let mut occupied = occupied.remove(&num).unwrap_or_default();
+      occupied.sort_by(|a, b| a.classroom.cmp(&b.classroom));
+      let free = known
+        .iter()
+        .filter(|room| !occupied.iter().any(|o| &o.classroom == *room))
+        .cloned()
+        .collect();
+      RoomsAtLesson { num, occupied, free }
+    })
+    .collect();
+
+  Rooms { uid: snapshot.map(|s| s.uid.clone()), date: *date, lessons }
+}
@@ -0,0 +1,51 @@
+use chrono::{DateTime, Utc};
+use maiq_parser::Snapshot;
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct TeacherSnapshot {
+  pub uid: String,
+  pub date: DateTime<Utc>,
+  pub parsed_date: DateTime<Utc>,
+  pub teacher: String,
+  pub lessons: Vec<TeacherLesson>,
+}
+
+#[derive(Serialize, PartialEq)]
+pub struct TeacherLesson {
+  pub num: u8,
+  pub group: String,
+  pub name: String,
+  pub teacher: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub classroom: Option<String>,
+}
+
+/// Teacher is matched case-insensitively by a part of the name, so `петрова` matches `Петрова Н.Г.`
+pub fn teacher_snapshot(snapshot: &Snapshot, teacher: &str) -> TeacherSnapshot {
+  let query = teacher.to_lowercase();
+  let mut lessons = snapshot
+    .groups
+    .iter()
+    .flat_map(|g| g.lessons.iter().map(move |l| (g, l)))
+    .filter_map(|(g, l)| {
+      let name = l.teacher.as_ref().filter(|t| t.to_lowercase().contains(&query))?;
+      Some(TeacherLesson {
+        num: l.num,
+        group: g.name.clone(),
+        name: l.name.clone(),
+        teacher: name.clone(),
+        classroom: l.classroom.clone(),
+      })
+    })
+    .collect::<Vec<_>>();
+  lessons.sort_by_key(|l| l.num);
+
+  TeacherSnapshot {
+    uid: snapshot.uid.clone(),
+    date: snapshot.date,
+    parsed_date: snapshot.parsed_date,
+    teacher: teacher.into(),
+    lessons,
+  }
+}
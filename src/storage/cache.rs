@@ -6,16 +6,26 @@ use chrono::{DateTime, Duration, Utc};
 use maiq_api_wrapper::Poll;
 use maiq_parser::compare::distinct;
 use maiq_parser::{snapshot_from_remote, utils::time::*, Fetch, Snapshot};
+use serde::{Serialize, Serializer};
 
-use tokio::time;
-use tokio::{sync::RwLock, time::Interval};
+use thiserror::Error;
+use tokio::sync::broadcast::{self, Receiver, Sender};
+use tokio::sync::watch;
+use tokio::sync::RwLock;
 
-use crate::{api::error::ApiError, env, storage::MongoPool};
+use crate::{api::error::ApiError, env, metrics, storage::MongoPool};
 
 use super::SnapshotPool;
 
-pub fn interval() -> Interval {
-  time::interval(std::time::Duration::from_secs(env::update_rate()))
+pub fn fetch_name(fetch: Fetch) -> &'static str {
+  match fetch {
+    Fetch::Today => "today",
+    Fetch::Next => "next",
+  }
+}
+
+fn serialize_fetch<S: Serializer>(fetch: &Fetch, serializer: S) -> Result<S::Ok, S::Error> {
+  serializer.serialize_str(fetch_name(*fetch))
 }
 
 struct CachedSnapshot {
@@ -43,27 +53,82 @@ impl From<Snapshot> for CachedSnapshot {
   }
 }
 
+#[derive(Clone, Serialize)]
+pub struct SnapshotUpdate {
+  #[serde(serialize_with = "serialize_fetch")]
+  pub fetch: Fetch,
+  pub uid: String,
+  pub date: DateTime<Utc>,
+  pub changes: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct CacheEntry {
+  pub uid: String,
+  pub date: DateTime<Utc>,
+  pub added: DateTime<Utc>,
+  pub age_secs: i64,
+  /// Will be dropped on the next purge, which happens only when cache is over `CACHE_SIZE`
+  pub expired: bool,
+}
+
+#[derive(Serialize)]
+pub struct Refreshed {
+  pub fetch: &'static str,
+  pub uid: Option<String>,
+  pub changes: Vec<String>,
+  pub error: Option<String>,
+}
+
+#[derive(Clone, Default, Serialize)]
+pub struct UpdaterStatus {
+  pub last_success: Option<DateTime<Utc>>,
+  pub last_failure: Option<DateTime<Utc>>,
+  pub last_error: Option<String>,
+  pub consecutive_failures: u32,
+  pub next_update: Option<DateTime<Utc>>,
+}
+
+#[derive(Error, Debug)]
+enum UpdateError {
+  #[error("Failed to parse {0} snapshot: {1}")]
+  Parser(&'static str, String),
+
+  #[error(transparent)]
+  Database(#[from] ApiError),
+}
+
 pub struct CachePool {
   cached: Vec<CachedSnapshot>,
   poll: Poll,
-  interval: Interval,
+  /// Snapshots replaced in poll by the last update, `(today, next)`
+  replaced: (Option<Snapshot>, Option<Snapshot>),
+  updates: Sender<SnapshotUpdate>,
+  /// Poll revision, starts from the launch time in ms so tokens from previous launches never match
+  revision: watch::Sender<u64>,
+  status: UpdaterStatus,
   cache_size: usize,
   cache_age_limit: Duration,
   db: MongoPool,
 }
 
 impl CachePool {
+  /// Restores the poll state, but doesn't fetch anything: the first `update_tick` is up to the caller,
+  /// so listeners of `subscribe` can be attached before it
   pub async fn new(mongo: MongoPool) -> Arc<RwLock<Self>> {
     let mut pool = Self {
-      interval: interval(),
       cached: vec![],
       cache_size: env::cache_size(),
       cache_age_limit: *env::cache_age_limit(),
       poll: Poll::default(),
+      replaced: (None, None),
+      updates: broadcast::channel(16).0,
+      revision: watch::channel(Utc::now().timestamp_millis() as u64).0,
+      status: UpdaterStatus::default(),
       db: mongo,
     };
 
-    pool.update_tick().await;
+    pool.restore().await;
     Arc::new(RwLock::new(pool))
   }
 
@@ -71,52 +136,321 @@ impl CachePool {
     self.poll.clone()
   }
 
+  pub fn subscribe(&self) -> Receiver<SnapshotUpdate> {
+    self.updates.subscribe()
+  }
+
+  pub fn watch_poll(&self) -> watch::Receiver<u64> {
+    self.revision.subscribe()
+  }
+
+  pub fn poll_revision(&self) -> u64 {
+    *self.revision.borrow()
+  }
+
+  /// Wakes up everyone waiting for a newer poll
+  pub fn notify_poll(&self) {
+    self.revision.send_modify(|rev| *rev += 1);
+  }
+
+  /// Time of the last update tick without errors
+  pub fn last_update(&self) -> Option<DateTime<Utc>> {
+    self.status.last_success
+  }
+
+  pub fn status(&self) -> UpdaterStatus {
+    self.status.clone()
+  }
+
+  /// Time until the next run by `UPDATE_SCHEDULE`, doubled for every consecutive failed tick up to `UPDATE_BACKOFF_LIMIT_SECS`
+  pub fn next_delay(&self) -> Duration {
+    let now = now().naive_utc();
+    let delay = env::update_schedule().next_run(now) - now;
+    let factor = 1 << self.status.consecutive_failures.min(16);
+    match self.status.consecutive_failures {
+      0 => delay,
+      _ => (delay * factor).min(*env::update_backoff_limit()).max(delay),
+    }
+  }
+
+  /// Time of the next planned update tick
+  pub fn next_run(&self) -> DateTime<Utc> {
+    self.status.next_update.unwrap_or_else(now)
+  }
+
+  pub fn poll_snapshot(&self, fetch: Fetch) -> Option<&Snapshot> {
+    match fetch {
+      Fetch::Today => self.poll.today_snapshot.as_ref(),
+      Fetch::Next => self.poll.next_snapshot.as_ref(),
+    }
+  }
+
+  /// Snapshot which was replaced by the one in poll in the last update
+  pub fn replaced_snapshot(&self, fetch: Fetch) -> Option<&Snapshot> {
+    match fetch {
+      Fetch::Today => self.replaced.0.as_ref(),
+      Fetch::Next => self.replaced.1.as_ref(),
+    }
+  }
+
+  pub fn next_update(&self) -> DateTime<Utc> {
+    self.poll.next_update
+  }
+
+  pub fn poll_changes(&self, fetch: Fetch) -> &[String] {
+    match fetch {
+      Fetch::Today => &self.poll.today_changes,
+      Fetch::Next => &self.poll.next_changes,
+    }
+  }
+
+  pub fn cached_count(&self) -> usize {
+    self.cached.len()
+  }
+
   pub fn collect_all(&self) -> Vec<Snapshot> {
     self.cached.iter().map(|s| s.snapshot.clone()).collect()
   }
 
-  pub async fn update_tick(&mut self) {
-    info!("Updating cache..");
-    self.purge();
+  pub fn entries(&self) -> Vec<CacheEntry> {
+    let today = now_date();
+    self
+      .cached
+      .iter()
+      .map(|s| CacheEntry {
+        uid: s.uid.clone(),
+        date: s.date,
+        added: s.added,
+        age_secs: s.since_added().num_seconds(),
+        expired: s.since_added() >= self.cache_age_limit && s.date < today,
+      })
+      .collect()
+  }
 
-    _ = self.update(Fetch::Today).await;
-    _ = self.update(Fetch::Next).await;
+  pub fn cache_age_limit(&self) -> Duration {
+    self.cache_age_limit
+  }
 
-    let next_update = now() + chrono::Duration::from_std(self.interval.period()).unwrap() + Duration::seconds(5);
-    self.poll.next_update = next_update;
-    info!("Poll updated has been updated to:");
-    info!("Today: {:?}", self.poll.today_changes);
-    info!("Next: {:?}", self.poll.next_changes);
+  pub fn evict(&mut self, uid: &str) -> bool {
+    let len = self.cached.len();
+    self.cached.retain(|s| s.uid != uid);
+    let removed = len != self.cached.len();
+    if removed {
+      info!("Evicted snapshot {} from cache", uid);
+    }
+    removed
+  }
+
+  /// Drops all cached snapshots, returns how many were there
+  pub fn clear(&mut self) -> usize {
+    let len = self.cached.len();
+    self.cached.clear();
+    info!("Removed all {} snapshots from cache", len);
+    len
   }
 
-  pub fn reset(&mut self) {
-    self.poll = Poll::default();
+  /// Scheduled update: refreshes both snapshots and plans the next tick
+  pub async fn update_tick(pool: &RwLock<Self>) {
+    info!("Updating cache..");
+    Self::refresh(pool, &[Fetch::Today, Fetch::Next]).await;
+
+    let mut pool = pool.write().await;
+    let next_update = now() + pool.next_delay();
+    pool.poll.next_update = next_update + Duration::seconds(5);
+    pool.status.next_update = Some(next_update);
+    pool.notify_poll();
+    info!("Poll updated has been updated to:");
+    info!("Today: {:?}", pool.poll.today_changes);
+    info!("Next: {:?}", pool.poll.next_changes);
   }
 
-  async fn update(&mut self, fetch: Fetch) -> Result<(), ApiError> {
-    let snapshot = snapshot_from_remote(&fetch).await.ok();
+  /// Fetches and applies the snapshots right away, without moving the planned tick.
+  /// Remote parsing and database round-trips run without holding the lock, only the final apply takes the write guard.
+  /// A failed fetch keeps the previous poll state and counts towards the backoff
+  pub async fn refresh(pool: &RwLock<Self>, fetches: &[Fetch]) -> Vec<Refreshed> {
+    let start = std::time::Instant::now();
+    let mut db = {
+      let mut pool = pool.write().await;
+      pool.purge();
+      pool.db.clone()
+    };
+
+    let mut fetched = vec![];
+    for fetch in fetches {
+      fetched.push((*fetch, Self::fetch(*fetch, &mut db).await));
+    }
 
-    info!("Parsed snapshot {}", snapshot.as_ref().map(|s| s.uid.as_str()).unwrap_or("-"));
-    if let Some(s) = snapshot.as_ref() {
-      self.save(s).await?;
-      if self.db.by_uid(&s.uid).await?.is_none() {
-        self.db.save(s).await?;
+    let mut pool = pool.write().await;
+    let mut errors = vec![];
+    let mut refreshed = vec![];
+    for (fetch, res) in fetched {
+      let mut entry = Refreshed { fetch: fetch_name(fetch), uid: None, changes: vec![], error: None };
+      match res {
+        Ok(snapshot) => {
+          entry.uid = Some(snapshot.uid.clone());
+          entry.changes = pool.apply(fetch, snapshot);
+        }
+        Err(err) => {
+          entry.error = Some(err.to_string());
+          errors.push(err.to_string());
+        }
       }
+      refreshed.push(entry);
     }
 
-    match fetch {
-      Fetch::Today => self.poll.today_changes = distinct(self.poll.today_snapshot.as_ref(), snapshot.as_ref()),
-      Fetch::Next => self.poll.next_changes = distinct(self.poll.next_snapshot.as_ref(), snapshot.as_ref()),
+    let is_ok = errors.is_empty();
+    let result = metrics::labels(&[("result", if is_ok { "success" } else { "failure" })]);
+    metrics::observe("maiq_update_tick_duration_seconds", result, start.elapsed().as_secs_f64());
+    match is_ok {
+      true => {
+        pool.status.last_success = Some(now());
+        pool.status.consecutive_failures = 0;
+        metrics::set("maiq_update_last_success_timestamp_seconds", String::new(), Utc::now().timestamp() as f64);
+      }
+      false => {
+        pool.status.last_failure = Some(now());
+        pool.status.last_error = Some(errors.join("; "));
+        pool.status.consecutive_failures += 1;
+        error!("Update tick failed ({} in a row): {}", pool.status.consecutive_failures, errors.join("; "));
+      }
+    }
+
+    drop(pool);
+    for entry in refreshed.iter() {
+      if let Some(uid) = entry.uid.as_ref() {
+        if let Err(err) = db.save_poll_state(entry.fetch, uid).await {
+          warn!("Can't save {} poll state: {}", entry.fetch, err);
+        }
+      }
     }
 
+    refreshed
+  }
+
+  /// Puts manually published snapshot into poll if it's for today or next day
+  pub fn publish(&mut self, snapshot: Snapshot) -> Vec<String> {
+    let today = now_date();
+    let next = self.poll.next_snapshot.as_ref().map(|s| s.date);
+    let fetch = match snapshot.date {
+      date if date == today => Some(Fetch::Today),
+      date if date > today && next.unwrap_or(date) == date => Some(Fetch::Next),
+      _ => None,
+    };
+
     match fetch {
-      Fetch::Today => self.poll.today_snapshot = snapshot,
-      Fetch::Next => self.poll.next_snapshot = snapshot,
+      Some(fetch) => {
+        let changes = self.apply(fetch, snapshot);
+        self.notify_poll();
+        changes
+      }
+      None => vec![],
     }
+  }
 
+  /// Drops poll state both in memory and in the database, so it isn't restored on restart
+  pub async fn reset(pool: &RwLock<Self>) -> Result<(), ApiError> {
+    let db = {
+      let mut pool = pool.write().await;
+      pool.poll = Poll::default();
+      pool.replaced = (None, None);
+      pool.notify_poll();
+      pool.db.clone()
+    };
+
+    db.clear_poll_state().await?;
     Ok(())
   }
 
+  async fn fetch(fetch: Fetch, db: &mut MongoPool) -> Result<Snapshot, UpdateError> {
+    let name = fetch_name(fetch);
+    let labels = metrics::labels(&[("fetch", name)]);
+    let snapshot = match snapshot_from_remote(&fetch).await {
+      Ok(s) => s,
+      Err(err) => {
+        metrics::inc("maiq_parser_failures_total", labels);
+        return Err(UpdateError::Parser(name, format!("{:?}", err)));
+      }
+    };
+
+    metrics::inc("maiq_parsed_snapshots_total", labels);
+    info!("Parsed snapshot {}", snapshot.uid);
+    if let Some(pinned) = db.pinned(snapshot.date.into()).await.map_err(ApiError::from)? {
+      info!("Snapshot for {} is pinned to {}, skipping parsed one", snapshot.date, pinned.uid);
+      return Ok(pinned);
+    }
+
+    // Saved again with the fresh `parsed_date` if another revision got ahead of it, e.g. a released manual one
+    let latest = db.by_date(snapshot.date.into()).await.map_err(ApiError::from)?;
+    if latest.map(|s| s.uid != snapshot.uid).unwrap_or(true) {
+      db.save(&snapshot).await?;
+    }
+
+    Ok(snapshot)
+  }
+
+  /// Returns changes against the previous snapshot
+  fn apply(&mut self, fetch: Fetch, snapshot: Snapshot) -> Vec<String> {
+    self.insert(&snapshot);
+
+    let (changes, prev, replaced) = match fetch {
+      Fetch::Today => (&mut self.poll.today_changes, &mut self.poll.today_snapshot, &mut self.replaced.0),
+      Fetch::Next => (&mut self.poll.next_changes, &mut self.poll.next_snapshot, &mut self.replaced.1),
+    };
+
+    *changes = distinct(prev.as_ref(), Some(&snapshot));
+    let is_new = prev.as_ref().map(|p| p.uid != snapshot.uid).unwrap_or(true);
+    if is_new || !changes.is_empty() {
+      let update = SnapshotUpdate { fetch, uid: snapshot.uid.clone(), date: snapshot.date, changes: changes.clone() };
+      _ = self.updates.send(update);
+    }
+
+    *replaced = prev.replace(snapshot);
+    changes.clone()
+  }
+
+  /// Picks up snapshots the poll was computed against before restart, if they are still actual
+  async fn restore(&mut self) {
+    let today = now_date();
+    for fetch in [Fetch::Today, Fetch::Next] {
+      let snapshot = match self.db.poll_state(fetch_name(fetch)).await {
+        Ok(Some(s)) => s,
+        Ok(None) => continue,
+        Err(err) => {
+          warn!("Can't restore {} poll state: {}", fetch_name(fetch), err);
+          continue;
+        }
+      };
+
+      let actual = match fetch {
+        Fetch::Today => snapshot.date == today,
+        Fetch::Next => snapshot.date > today,
+      };
+
+      if actual {
+        info!("Restored {} poll state from snapshot {}", fetch_name(fetch), snapshot.uid);
+        self.insert(&snapshot);
+        match fetch {
+          Fetch::Today => self.poll.today_snapshot = Some(snapshot),
+          Fetch::Next => self.poll.next_snapshot = Some(snapshot),
+        }
+      }
+    }
+  }
+
+  fn insert(&mut self, snapshot: &Snapshot) {
+    if self.cached.iter().any(|s| *s.uid.as_str() == snapshot.uid) {
+      return;
+    }
+
+    if let Some(index) = self.cached.iter().position(|s| s.date == snapshot.date) {
+      info!("Removing snapshot by date {} due to receiving new", snapshot.date);
+      self.cached.remove(index);
+    }
+
+    info!("Snapshot {} saved to cache", snapshot.uid);
+    self.cached.push(snapshot.clone().into());
+  }
+
   fn purge(&mut self) {
     let len = self.cached.len();
     let now = now_date();
@@ -132,18 +466,8 @@ impl CachePool {
 #[rocket::async_trait]
 impl SnapshotPool for CachePool {
   async fn save(&mut self, snapshot: &Snapshot) -> Result<(), ApiError> {
-    if self.cached.iter().any(|s| *s.uid.as_str() == snapshot.uid) {
-      return Ok(());
-    }
-
-    if let Some(index) = self.cached.iter().position(|s| s.date == snapshot.date) {
-      info!("Removing snapshot by date {} due to receiving new", snapshot.date);
-      self.cached.remove(index);
-    }
-
-    info!("Snapshot {} saved to cache", snapshot.uid);
-    self.cached.push(snapshot.clone().into());
-    return Ok(());
+    self.insert(snapshot);
+    Ok(())
   }
 
   async fn latest(&self, mode: Fetch) -> Result<Option<Snapshot>, ApiError> {
@@ -154,6 +478,7 @@ impl SnapshotPool for CachePool {
       Fetch::Next => iter.find(|s| s.date > today).map(|c| c.snapshot.clone()),
     };
 
+    metrics::cache_lookup("latest", res.is_some());
     Ok(res)
   }
 
@@ -164,6 +489,7 @@ impl SnapshotPool for CachePool {
       .find(|s| s.uid.as_str() == uid.as_ref())
       .map(|c| c.snapshot.clone());
 
+    metrics::cache_lookup("by_uid", res.is_some());
     Ok(res)
   }
 }
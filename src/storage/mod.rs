@@ -5,8 +5,9 @@ use mongodb::Collection;
 
 use crate::api::error::ApiError;
 
+use chrono::Utc;
 use maiq_parser::{Fetch, Group, Snapshot};
-use mongodb::bson::{doc, DateTime};
+use mongodb::bson::{doc, oid::ObjectId, DateTime};
 use serde::{Deserialize, Serialize};
 
 use self::mongo::MongoPool;
@@ -24,6 +25,8 @@ pub struct SnapshotModel {
   pub date: DateTime,
   pub parsed_date: DateTime,
   pub groups: Vec<Group>,
+  #[serde(default)]
+  pub manual: bool,
 }
 
 impl From<SnapshotModel> for Snapshot {
@@ -40,12 +43,72 @@ impl From<SnapshotModel> for Option<Snapshot> {
 
 impl From<&Snapshot> for SnapshotModel {
   fn from(s: &Snapshot) -> Self {
-    Self { uid: s.uid.clone(), date: s.date.into(), parsed_date: s.parsed_date.into(), groups: s.groups.clone() }
+    Self { uid: s.uid.clone(), date: s.date.into(), parsed_date: s.parsed_date.into(), groups: s.groups.clone(), manual: false }
   }
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct RevisionModel {
+  pub uid: String,
+  pub parsed_date: DateTime,
+  #[serde(default)]
+  pub manual: bool,
+}
+
+#[derive(Serialize)]
+pub struct Revision {
+  pub uid: String,
+  pub parsed_date: chrono::DateTime<Utc>,
+  pub manual: bool,
+}
+
+impl From<RevisionModel> for Revision {
+  fn from(val: RevisionModel) -> Self {
+    Revision { uid: val.uid, parsed_date: val.parsed_date.to_chrono(), manual: val.manual }
+  }
+}
+
+/// Snapshot the poll was last computed against, `_id` is `today` or `next`
+#[derive(Serialize, Deserialize)]
+pub struct PollStateModel {
+  #[serde(rename = "_id")]
+  pub fetch: String,
+  pub uid: String,
+}
+
+/// Manually published snapshot which wins over parsed ones for the date
+#[derive(Serialize, Deserialize)]
+pub struct PinModel {
+  pub date: DateTime,
+  pub uid: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct WebhookModel {
+  #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+  pub id: Option<ObjectId>,
+  pub url: String,
+  pub secret: String,
+}
+
 impl MongoPool {
   fn get_snapshot_models(&self) -> Collection<SnapshotModel> {
     self.default_database().unwrap().collection("snapshots")
   }
+
+  fn get_webhook_models(&self) -> Collection<WebhookModel> {
+    self.default_database().unwrap().collection("webhooks")
+  }
+
+  fn get_revision_models(&self) -> Collection<RevisionModel> {
+    self.default_database().unwrap().collection("snapshots")
+  }
+
+  fn get_poll_state_models(&self) -> Collection<PollStateModel> {
+    self.default_database().unwrap().collection("poll")
+  }
+
+  fn get_pin_models(&self) -> Collection<PinModel> {
+    self.default_database().unwrap().collection("pins")
+  }
 }
@@ -1,12 +1,15 @@
 use std::ops::Deref;
 
 use maiq_parser::{utils::time::*, Fetch, Snapshot};
-use mongodb::bson::doc;
 use mongodb::bson::DateTime;
-use mongodb::options::{ClientOptions, FindOneAndReplaceOptions, FindOneOptions};
+use mongodb::bson::{doc, oid::ObjectId};
+use mongodb::options::{ClientOptions, FindOneAndReplaceOptions, FindOneOptions, FindOptions};
 
-use crate::env;
-use crate::{api::error::ApiError, storage::SnapshotModel};
+use crate::{
+  api::error::ApiError,
+  storage::{PinModel, PollStateModel, Revision, SnapshotModel, WebhookModel},
+};
+use crate::{env, metrics};
 
 use super::SnapshotPool;
 
@@ -36,6 +39,7 @@ impl MongoPool {
   }
 
   async fn get_latest_today(&self) -> Result<Option<Snapshot>, MongoError> {
+    let _timer = metrics::mongo_timer("latest_today");
     let snapshots = self.get_snapshot_models();
     let today = DateTime::from_chrono(now_date());
     let opts = FindOneOptions::builder().sort(doc! { "parsed_date": -1 }).build();
@@ -47,6 +51,7 @@ impl MongoPool {
   }
 
   async fn get_latest_next(&self) -> Result<Option<Snapshot>, MongoError> {
+    let _timer = metrics::mongo_timer("latest_next");
     let snapshots = self.get_snapshot_models();
     let time = DateTime::from_chrono(now_date_offset(1));
     let opts = FindOneOptions::builder().sort(doc! { "parsed_date": -1 }).build();
@@ -58,6 +63,7 @@ impl MongoPool {
   }
 
   pub async fn by_date(&self, date: DateTime) -> Result<Option<Snapshot>, MongoError> {
+    let _timer = metrics::mongo_timer("by_date");
     let snapshots = self.get_snapshot_models();
     let opts = FindOneOptions::builder().sort(doc! { "parsed_date": -1 }).build();
     let res = snapshots
@@ -66,19 +72,184 @@ impl MongoPool {
       .and_then(Into::into);
     Ok(res)
   }
+
+  /// Latest revisions of snapshots within the dates, sorted by date. With `group`, only revisions containing it
+  pub async fn range(
+    &self,
+    from: DateTime,
+    to: DateTime,
+    group: Option<&str>,
+    skip: i64,
+    limit: i64,
+  ) -> Result<Vec<Snapshot>, MongoError> {
+    let _timer = metrics::mongo_timer("range");
+    let snapshots = self.get_snapshot_models();
+    let mut pipeline = vec![
+      doc! { "$match": { "date": { "$gte": from, "$lte": to } } },
+      doc! { "$sort": { "date": 1, "parsed_date": -1 } },
+      doc! { "$group": { "_id": "$date", "snapshot": { "$first": "$$ROOT" } } },
+      doc! { "$replaceRoot": { "newRoot": "$snapshot" } },
+    ];
+    if let Some(group) = group {
+      pipeline.push(doc! { "$match": { "groups.name": group } });
+    }
+    pipeline.extend([doc! { "$sort": { "date": 1 } }, doc! { "$skip": skip }, doc! { "$limit": limit }]);
+
+    let mut cur = snapshots
+      .aggregate(pipeline, None)
+      .await?
+      .with_type::<SnapshotModel>();
+    let mut res = vec![];
+    while cur.advance().await? {
+      res.push(cur.deserialize_current()?.into());
+    }
+    Ok(res)
+  }
+
+  pub async fn classrooms(&self) -> Result<Vec<String>, MongoError> {
+    let _timer = metrics::mongo_timer("classrooms");
+    let snapshots = self.get_snapshot_models();
+    let res = snapshots.distinct("groups.lessons.classroom", None, None).await?;
+    Ok(res.into_iter().filter_map(|x| x.as_str().map(Into::into)).collect())
+  }
+
+  pub async fn revisions(&self, date: DateTime) -> Result<Vec<Revision>, MongoError> {
+    let _timer = metrics::mongo_timer("revisions");
+    let revisions = self.get_revision_models();
+    let opts = FindOptions::builder()
+      .sort(doc! { "parsed_date": -1 })
+      .projection(doc! { "uid": 1, "parsed_date": 1, "manual": 1 })
+      .build();
+    let mut cur = revisions.find(doc! { "date": date }, opts).await?;
+    let mut res = vec![];
+    while cur.advance().await? {
+      res.push(cur.deserialize_current()?.into());
+    }
+    Ok(res)
+  }
+
+  pub async fn poll_state(&self, fetch: &str) -> Result<Option<Snapshot>, MongoError> {
+    let _timer = metrics::mongo_timer("poll_state");
+    let state = match self
+      .get_poll_state_models()
+      .find_one(doc! { "_id": fetch }, None)
+      .await?
+    {
+      Some(state) => state,
+      None => return Ok(None),
+    };
+
+    let res = self
+      .get_snapshot_models()
+      .find_one(doc! { "uid": state.uid }, None)
+      .await?
+      .and_then(Into::into);
+    Ok(res)
+  }
+
+  pub async fn save_poll_state(&self, fetch: &str, uid: &str) -> Result<(), MongoError> {
+    let _timer = metrics::mongo_timer("save_poll_state");
+    let model = PollStateModel { fetch: fetch.into(), uid: uid.into() };
+    let opts = FindOneAndReplaceOptions::builder().upsert(true).build();
+    self
+      .get_poll_state_models()
+      .find_one_and_replace(doc! { "_id": fetch }, model, opts)
+      .await?;
+    Ok(())
+  }
+
+  pub async fn clear_poll_state(&self) -> Result<(), MongoError> {
+    let _timer = metrics::mongo_timer("clear_poll_state");
+    self.get_poll_state_models().delete_many(doc! {}, None).await?;
+    Ok(())
+  }
+
+  /// Saves the snapshot as manual and makes it win over parsed ones for its date
+  pub async fn pin(&self, snapshot: &Snapshot) -> Result<(), MongoError> {
+    let _timer = metrics::mongo_timer("pin");
+    self
+      .save_model(SnapshotModel { manual: true, ..snapshot.into() })
+      .await?;
+
+    let date = snapshot.date.into();
+    let opts = FindOneAndReplaceOptions::builder().upsert(true).build();
+    self
+      .get_pin_models()
+      .find_one_and_replace(doc! { "date": date }, PinModel { date, uid: snapshot.uid.clone() }, opts)
+      .await?;
+    Ok(())
+  }
+
+  pub async fn unpin(&self, date: DateTime) -> Result<bool, MongoError> {
+    let _timer = metrics::mongo_timer("unpin");
+    let res = self.get_pin_models().delete_one(doc! { "date": date }, None).await?;
+    Ok(res.deleted_count > 0)
+  }
+
+  pub async fn pinned(&self, date: DateTime) -> Result<Option<Snapshot>, MongoError> {
+    let _timer = metrics::mongo_timer("pinned");
+    let pin = match self.get_pin_models().find_one(doc! { "date": date }, None).await? {
+      Some(pin) => pin,
+      None => return Ok(None),
+    };
+
+    let res = self
+      .get_snapshot_models()
+      .find_one(doc! { "uid": pin.uid }, None)
+      .await?
+      .and_then(Into::into);
+    Ok(res)
+  }
+
+  pub async fn ping(&self) -> Result<(), MongoError> {
+    let _timer = metrics::mongo_timer("ping");
+    self
+      .default_database()
+      .unwrap()
+      .run_command(doc! { "ping": 1 }, None)
+      .await?;
+    Ok(())
+  }
+
+  /// Replaces the snapshot with the same uid, so re-saving bumps its `parsed_date`
+  async fn save_model(&self, model: SnapshotModel) -> Result<(), MongoError> {
+    let opts = FindOneAndReplaceOptions::builder().upsert(true).build();
+    self
+      .get_snapshot_models()
+      .find_one_and_replace(doc! { "uid": model.uid.as_str() }, model, opts)
+      .await?;
+    Ok(())
+  }
+
+  pub async fn webhooks(&self) -> Result<Vec<WebhookModel>, MongoError> {
+    let _timer = metrics::mongo_timer("webhooks");
+    let mut cur = self.get_webhook_models().find(None, None).await?;
+    let mut res = vec![];
+    while cur.advance().await? {
+      res.push(cur.deserialize_current()?);
+    }
+    Ok(res)
+  }
+
+  pub async fn add_webhook(&self, url: String, secret: String) -> Result<Option<ObjectId>, MongoError> {
+    let _timer = metrics::mongo_timer("add_webhook");
+    let model = WebhookModel { id: None, url, secret };
+    let res = self.get_webhook_models().insert_one(model, None).await?;
+    Ok(res.inserted_id.as_object_id())
+  }
+
+  pub async fn remove_webhook(&self, id: ObjectId) -> Result<bool, MongoError> {
+    let _timer = metrics::mongo_timer("remove_webhook");
+    let res = self.get_webhook_models().delete_one(doc! { "_id": id }, None).await?;
+    Ok(res.deleted_count > 0)
+  }
 }
 
 #[rocket::async_trait]
 impl SnapshotPool for MongoPool {
   async fn save(&mut self, snapshot: &Snapshot) -> Result<(), ApiError> {
-    let snapshots = self.get_snapshot_models();
-    let model = SnapshotModel::from(snapshot);
-    let opts = FindOneAndReplaceOptions::builder().upsert(true).build();
-    snapshots
-      .find_one_and_replace(doc! { "date": model.date }, model, opts)
-      .await
-      .unwrap();
-
+    let _timer = metrics::mongo_timer("save");
+    self.save_model(snapshot.into()).await?;
     Ok(())
   }
 
@@ -90,6 +261,7 @@ impl SnapshotPool for MongoPool {
   }
 
   async fn by_uid<T: AsRef<str> + Send>(&self, uid: T) -> Result<Option<Snapshot>, ApiError> {
+    let _timer = metrics::mongo_timer("by_uid");
     let snapshots = self.get_snapshot_models();
     let mut cur = snapshots.find(doc! { "uid": uid.as_ref() }, None).await?;
     if !cur.advance().await? {
@@ -0,0 +1,190 @@
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use rocket::serde::json::serde_json;
+use sha2::Sha256;
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+
+use crate::{
+  env,
+  storage::{cache::SnapshotUpdate, mongo::MongoPool, WebhookModel},
+};
+
+const ATTEMPTS: u32 = 4;
+
+#[derive(Clone)]
+pub struct Webhook {
+  pub url: String,
+  pub secret: String,
+}
+
+impl From<WebhookModel> for Webhook {
+  fn from(model: WebhookModel) -> Self {
+    Webhook { url: model.url, secret: model.secret }
+  }
+}
+
+/// `sha256=<hex>` HMAC of the request body, sent in `X-Maiq-Signature` header
+pub fn signature(secret: &str, body: &[u8]) -> String {
+  let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+  mac.update(body);
+  format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Takes the receiver instead of subscribing in the task, so updates sent before the task starts aren't lost
+pub fn startup_webhook_sender(mut updates: Receiver<SnapshotUpdate>, db: MongoPool) {
+  tokio::spawn(async move {
+    let client = Client::new();
+    loop {
+      let update = match updates.recv().await {
+        Ok(update) => update,
+        Err(RecvError::Lagged(skipped)) => {
+          warn!("Webhook sender lagged behind, {} updates skipped", skipped);
+          continue;
+        }
+        Err(RecvError::Closed) => break,
+      };
+
+      let body = match serde_json::to_vec(&update) {
+        Ok(body) => body,
+        Err(e) => {
+          error!("Can't serialize webhook payload: {}", e);
+          continue;
+        }
+      };
+
+      let hooks = match webhooks(&db).await {
+        Ok(hooks) => hooks,
+        Err(e) => {
+          error!("Can't load webhooks: {}", e);
+          continue;
+        }
+      };
+
+      for hook in hooks {
+        tokio::spawn(deliver(client.clone(), hook, body.clone()));
+      }
+    }
+  });
+}
+
+async fn webhooks(db: &MongoPool) -> Result<Vec<Webhook>, mongodb::error::Error> {
+  let mut hooks = env::webhooks()
+    .iter()
+    .map(|(url, secret)| Webhook { url: url.clone(), secret: secret.clone() })
+    .collect::<Vec<_>>();
+  hooks.extend(db.webhooks().await?.into_iter().map(Into::into));
+  Ok(hooks)
+}
+
+async fn deliver(client: Client, hook: Webhook, body: Vec<u8>) {
+  let signature = signature(&hook.secret, &body);
+  for attempt in 1..=ATTEMPTS {
+    let res = client
+      .post(&hook.url)
+      .header("Content-Type", "application/json")
+      .header("X-Maiq-Event", "snapshot")
+      .header("X-Maiq-Signature", &signature)
+      .timeout(Duration::from_secs(10))
+      .body(body.clone())
+      .send()
+      .await;
+
+    match res {
+      Ok(res) if res.status().is_success() => {
+        info!("Webhook {} delivered", hook.url);
+        return;
+      }
+      Ok(res) => warn!("Webhook {} responded with {} (attempt {}/{})", hook.url, res.status(), attempt, ATTEMPTS),
+      Err(e) => warn!("Webhook {} failed: {} (attempt {}/{})", hook.url, e, attempt, ATTEMPTS),
+    }
+
+    if attempt < ATTEMPTS {
+      tokio::time::sleep(Duration::from_secs(2u64.pow(attempt - 1))).await;
+    }
+  }
+
+  error!("Webhook {} wasn't delivered after {} attempts", hook.url, ATTEMPTS);
+}
+
+#[cfg(test)]
+mod tests {
+  use std::io::{BufRead, BufReader, Read, Write};
+  use std::net::TcpListener;
+  use std::sync::{Arc, Mutex};
+
+  use super::*;
+
+  struct Received {
+    signature: Option<String>,
+    body: Vec<u8>,
+  }
+
+  /// Stand-in receiver, answers 500 to the first `failures` requests and 200 afterwards
+  fn receiver(failures: usize) -> (String, Arc<Mutex<Vec<Received>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    let received = Arc::new(Mutex::new(vec![]));
+    let log = received.clone();
+    std::thread::spawn(move || {
+      for stream in listener.incoming() {
+        let mut stream = stream.unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let (mut len, mut signature) = (0, None);
+        loop {
+          let mut line = String::new();
+          reader.read_line(&mut line).unwrap();
+          let line = line.trim_end();
+          if line.is_empty() {
+            break;
+          }
+          if let Some((name, value)) = line.split_once(": ") {
+            match name.to_lowercase().as_str() {
+              "content-length" => len = value.parse().unwrap(),
+              "x-maiq-signature" => signature = Some(value.to_string()),
+              _ => (),
+            }
+          }
+        }
+
+        let mut body = vec![0; len];
+        reader.read_exact(&mut body).unwrap();
+        let mut log = log.lock().unwrap();
+        log.push(Received { signature, body });
+        let status = if log.len() <= failures { "500 Internal Server Error" } else { "200 OK" };
+        write!(stream, "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status).unwrap();
+      }
+    });
+    (url, received)
+  }
+
+  #[test]
+  fn signature_matches_rfc4231_vector() {
+    assert_eq!(
+      signature("Jefe", b"what do ya want for nothing?"),
+      "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+    );
+  }
+
+  #[tokio::test]
+  async fn deliver_retries_until_success() {
+    let (url, received) = receiver(2);
+    let body = br#"{"fetch":"today","uid":"abc"}"#.to_vec();
+    deliver(Client::new(), Webhook { url, secret: "secret".into() }, body.clone()).await;
+
+    let received = received.lock().unwrap();
+    assert_eq!(received.len(), 3);
+    for req in received.iter() {
+      assert_eq!(req.body, body);
+      assert_eq!(req.signature, Some(signature("secret", &body)));
+    }
+  }
+
+  #[tokio::test]
+  async fn deliver_gives_up_after_all_attempts() {
+    let (url, received) = receiver(usize::MAX);
+    deliver(Client::new(), Webhook { url, secret: "secret".into() }, b"{}".to_vec()).await;
+    assert_eq!(received.lock().unwrap().len(), ATTEMPTS as usize);
+  }
+}
@@ -0,0 +1,215 @@
+use std::collections::BTreeSet;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use maiq_parser::{Fetch, Snapshot, TinySnapshot};
+use rocket::{
+  data::{IoHandler, IoStream},
+  futures::{SinkExt, StreamExt},
+  http::Status,
+  request::{FromRequest, Outcome},
+  response::{self, Responder},
+  serde::json::serde_json,
+  tokio::{
+    io, select,
+    sync::{broadcast::error::RecvError, RwLock},
+  },
+  Request, Response, Shutdown,
+};
+use serde::{Deserialize, Serialize};
+use tokio_tungstenite::{
+  tungstenite::{handshake::derive_accept_key, protocol::Role, Message},
+  WebSocketStream,
+};
+
+use crate::{
+  api::error::ApiError,
+  schedule::teacher::{teacher_snapshot, TeacherSnapshot},
+  storage::cache::{fetch_name, CachePool, SnapshotUpdate},
+};
+
+/// Handshake of a WebSocket upgrade request
+pub struct WebSocket {
+  accept: String,
+  peer: String,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for WebSocket {
+  type Error = ApiError;
+
+  async fn from_request(req: &'r Request<'_>) -> Outcome<WebSocket, Self::Error> {
+    let headers = req.headers();
+    let upgrade = headers.get("upgrade").any(|x| x.eq_ignore_ascii_case("websocket"));
+    match (upgrade, headers.get_one("sec-websocket-key"), headers.get_one("sec-websocket-version")) {
+      (true, Some(key), Some("13")) => Outcome::Success(WebSocket {
+        accept: derive_accept_key(key.as_bytes()),
+        peer: req.remote().map(|addr| addr.to_string()).unwrap_or_default(),
+      }),
+      _ => Outcome::Error((Status::UpgradeRequired, ApiError::UpgradeRequired)),
+    }
+  }
+}
+
+impl WebSocket {
+  pub fn channel(self, cache: Arc<RwLock<CachePool>>, shutdown: Shutdown) -> Channel {
+    Channel { accept: self.accept, connection: Connection { peer: self.peer, cache, shutdown } }
+  }
+}
+
+/// Switches the connection to WebSocket, Rocket sets `101 Switching Protocols` and upgrade headers by itself
+pub struct Channel {
+  accept: String,
+  connection: Connection,
+}
+
+impl<'r> Responder<'r, 'static> for Channel {
+  fn respond_to(self, _: &'r Request<'_>) -> response::Result<'static> {
+    Response::build()
+      .raw_header("Sec-WebSocket-Accept", self.accept)
+      .upgrade("websocket", self.connection)
+      .ok()
+  }
+}
+
+struct Connection {
+  peer: String,
+  cache: Arc<RwLock<CachePool>>,
+  shutdown: Shutdown,
+}
+
+#[rocket::async_trait]
+impl IoHandler for Connection {
+  async fn io(self: Pin<Box<Self>>, io: IoStream) -> io::Result<()> {
+    let Connection { peer, cache, mut shutdown } = *Pin::into_inner(self);
+    let socket = WebSocketStream::from_raw_socket(io, Role::Server, None).await;
+
+    info!("WebSocket client {} connected", peer);
+    let (mut sink, mut incoming) = socket.split();
+    let mut updates = cache.read().await.subscribe();
+    let mut subscriptions = Subscriptions::default();
+
+    loop {
+      let replies = select! {
+        message = incoming.next() => match message {
+          Some(Ok(Message::Text(text))) => vec![subscriptions.handle(&text)],
+          Some(Ok(Message::Close(_))) | None => break,
+          Some(Ok(_)) => continue,
+          Some(Err(e)) => {
+            warn!("WebSocket client {} error: {}", peer, e);
+            break;
+          }
+        },
+        update = updates.recv() => match update {
+          Ok(SnapshotUpdate { fetch, .. }) => {
+            let cache = cache.read().await;
+            match cache.poll_snapshot(fetch) {
+              Some(current) => subscriptions.changes(fetch, current, cache.replaced_snapshot(fetch)),
+              None => continue,
+            }
+          }
+          Err(RecvError::Lagged(skipped)) => {
+            warn!("WebSocket client {} lagged behind, {} updates skipped", peer, skipped);
+            continue;
+          }
+          Err(RecvError::Closed) => break,
+        },
+        _ = &mut shutdown => {
+          _ = sink.send(Message::Close(None)).await;
+          break;
+        }
+      };
+
+      for reply in replies {
+        let text = serde_json::to_string(&reply).unwrap_or_default();
+        if sink.send(Message::Text(text)).await.is_err() {
+          info!("WebSocket client {} disconnected", peer);
+          return Ok(());
+        }
+      }
+    }
+
+    info!("WebSocket client {} disconnected", peer);
+    Ok(())
+  }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+  Subscribe {
+    #[serde(default)]
+    groups: Vec<String>,
+    #[serde(default)]
+    teachers: Vec<String>,
+  },
+  Unsubscribe {
+    #[serde(default)]
+    groups: Vec<String>,
+    #[serde(default)]
+    teachers: Vec<String>,
+  },
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerMessage {
+  Subscriptions { groups: BTreeSet<String>, teachers: BTreeSet<String> },
+  Group { fetch: &'static str, snapshot: TinySnapshot },
+  Teacher { fetch: &'static str, snapshot: TeacherSnapshot },
+  Error { desc: String },
+}
+
+#[derive(Default)]
+struct Subscriptions {
+  groups: BTreeSet<String>,
+  teachers: BTreeSet<String>,
+}
+
+impl Subscriptions {
+  fn handle(&mut self, text: &str) -> ServerMessage {
+    let message = match serde_json::from_str(text) {
+      Ok(message) => message,
+      Err(e) => return ServerMessage::Error { desc: e.to_string() },
+    };
+
+    match message {
+      ClientMessage::Subscribe { groups, teachers } => {
+        let known = maiq_parser::env::groups();
+        if let Some(group) = groups.iter().find(|g| !known.contains(g)) {
+          return ServerMessage::Error { desc: format!("Group `{}` not found", group) };
+        }
+        self.groups.extend(groups);
+        self.teachers.extend(teachers);
+      }
+      ClientMessage::Unsubscribe { groups, teachers } => {
+        groups.iter().for_each(|g| _ = self.groups.remove(g));
+        teachers.iter().for_each(|t| _ = self.teachers.remove(t));
+      }
+    }
+
+    ServerMessage::Subscriptions { groups: self.groups.clone(), teachers: self.teachers.clone() }
+  }
+
+  /// Subscribed parts of the poll snapshot that differ from the snapshot it replaced
+  fn changes(&self, fetch: Fetch, current: &Snapshot, replaced: Option<&Snapshot>) -> Vec<ServerMessage> {
+    let name = fetch_name(fetch);
+
+    let group_uid = |tiny: &TinySnapshot| tiny.group.as_ref().map(|g| g.uid.clone());
+    let groups = self.groups.iter().filter_map(|group| {
+      let tiny = current.tiny(group);
+      let prev = replaced.and_then(|s| group_uid(&s.tiny(group)));
+      (group_uid(&tiny) != prev).then_some(ServerMessage::Group { fetch: name, snapshot: tiny })
+    });
+
+    let teachers = self.teachers.iter().filter_map(|teacher| {
+      let snapshot = teacher_snapshot(current, teacher);
+      let prev = replaced
+        .map(|s| teacher_snapshot(s, teacher).lessons)
+        .unwrap_or_default();
+      (snapshot.lessons != prev).then_some(ServerMessage::Teacher { fetch: name, snapshot })
+    });
+
+    groups.chain(teachers).collect()
+  }
+}